@@ -1,3 +1,6 @@
+-- The schema from before migrations were introduced.
+-- `IF NOT EXISTS` lets this adopt databases that were created by `create-tables.sql`.
+
 CREATE TABLE IF NOT EXISTS suggestions (
     id INTEGER PRIMARY KEY,
     user_id INTEGER NOT NULL,
@@ -12,6 +15,13 @@ CREATE TABLE IF NOT EXISTS suggestions (
     timestamp DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
 );
 
+CREATE TABLE IF NOT EXISTS suggestion_links (
+    id INTEGER PRIMARY KEY,
+    suggestion_id INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    platform TEXT NOT NULL
+);
+
 CREATE TABLE IF NOT EXISTS polls (
     id INTEGER PRIMARY KEY,
     message_id INTEGER NOT NULL,
@@ -32,4 +42,4 @@ CREATE TABLE IF NOT EXISTS deleted_suggestions (
     internal BOOLEAN NOT NULL,
     status INTEGER NOT NULL,
     timestamp DATETIME NOT NULL
-);
\ No newline at end of file
+);
@@ -0,0 +1,2 @@
+-- Records when a suggestion was approved, for the queue overview.
+ALTER TABLE suggestions ADD COLUMN approved_at DATETIME;
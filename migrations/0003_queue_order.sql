@@ -0,0 +1,7 @@
+-- Explicit queue positions set by facilitators. Suggestions without a position follow in submission order.
+ALTER TABLE suggestions ADD COLUMN queue_position INTEGER;
+
+-- Categories whose next scheduled pick is skipped.
+CREATE TABLE queue_skips (
+    internal BOOLEAN PRIMARY KEY
+);
@@ -0,0 +1,2 @@
+-- Opposing votes of pending polls, encoded like `votes`.
+ALTER TABLE polls ADD COLUMN downvotes TEXT;
@@ -0,0 +1,10 @@
+-- Reasons given by facilitators for vetoes.
+ALTER TABLE deleted_suggestions ADD COLUMN reason TEXT;
+
+-- Messages that couldn't be sent to users directly, shown the next time they use `/suggest`.
+CREATE TABLE notices (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    timestamp DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
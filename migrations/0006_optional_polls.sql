@@ -0,0 +1,24 @@
+-- Suggestions restored straight into the approved queue have no poll, so `poll_id` becomes optional.
+CREATE TABLE suggestions_new (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    username TEXT NOT NULL,
+    artist_name TEXT NOT NULL,
+    album_name TEXT NOT NULL,
+    links TEXT NOT NULL,
+    notes TEXT,
+    internal BOOLEAN NOT NULL,
+    poll_id INTEGER,
+    approved BOOLEAN NOT NULL DEFAULT FALSE,
+    timestamp DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    approved_at DATETIME,
+    queue_position INTEGER
+);
+
+INSERT INTO suggestions_new (id, user_id, username, artist_name, album_name, links, notes, internal, poll_id, approved, timestamp, approved_at, queue_position)
+SELECT id, user_id, username, artist_name, album_name, links, notes, internal, poll_id, approved, timestamp, approved_at, queue_position
+FROM suggestions;
+
+DROP TABLE suggestions;
+
+ALTER TABLE suggestions_new RENAME TO suggestions;
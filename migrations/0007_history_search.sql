@@ -0,0 +1,30 @@
+-- When a suggestion was moved to the archive. Unknown for suggestions archived before this migration.
+ALTER TABLE deleted_suggestions ADD COLUMN archived_at DATETIME;
+
+-- Full-text index of the archive for `/history`.
+CREATE VIRTUAL TABLE deleted_suggestions_search USING fts5(
+    artist_name,
+    album_name,
+    username,
+    content = 'deleted_suggestions',
+    content_rowid = 'id'
+);
+
+INSERT INTO deleted_suggestions_search (deleted_suggestions_search) VALUES ('rebuild');
+
+CREATE TRIGGER deleted_suggestions_search_insert AFTER INSERT ON deleted_suggestions BEGIN
+    INSERT INTO deleted_suggestions_search (rowid, artist_name, album_name, username)
+    VALUES (new.id, new.artist_name, new.album_name, new.username);
+END;
+
+CREATE TRIGGER deleted_suggestions_search_delete AFTER DELETE ON deleted_suggestions BEGIN
+    INSERT INTO deleted_suggestions_search (deleted_suggestions_search, rowid, artist_name, album_name, username)
+    VALUES ('delete', old.id, old.artist_name, old.album_name, old.username);
+END;
+
+CREATE TRIGGER deleted_suggestions_search_update AFTER UPDATE ON deleted_suggestions BEGIN
+    INSERT INTO deleted_suggestions_search (deleted_suggestions_search, rowid, artist_name, album_name, username)
+    VALUES ('delete', old.id, old.artist_name, old.album_name, old.username);
+    INSERT INTO deleted_suggestions_search (rowid, artist_name, album_name, username)
+    VALUES (new.id, new.artist_name, new.album_name, new.username);
+END;
@@ -0,0 +1,25 @@
+-- Users who may not submit suggestions.
+CREATE TABLE blocked_users (
+    user_id INTEGER PRIMARY KEY,
+    reason TEXT,
+    timestamp DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Artists who may not be suggested, by normalized name.
+CREATE TABLE blocked_artists (
+    normalized_name TEXT PRIMARY KEY,
+    artist_name TEXT NOT NULL,
+    reason TEXT,
+    timestamp DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Submissions rejected because of the blocklist. The names are unknown if the user was blocked before the form.
+CREATE TABLE blocked_attempts (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    username TEXT NOT NULL,
+    artist_name TEXT,
+    album_name TEXT,
+    internal BOOLEAN NOT NULL,
+    timestamp DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
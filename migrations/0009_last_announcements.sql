@@ -0,0 +1,5 @@
+-- The slot of the last announcement of each category. The internal cadence is derived from it.
+CREATE TABLE last_announcements (
+    internal BOOLEAN PRIMARY KEY,
+    timestamp DATETIME NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Every announcement slot the scheduler has handled, used to detect slots missed while the bot was offline.
+-- `status` is 0 if the slot was handled on time, 1 if it was posted late, 2 if it was skipped and 3 if posting failed.
+CREATE TABLE announcement_slots (
+    slot DATETIME PRIMARY KEY,
+    status INTEGER NOT NULL,
+    timestamp DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,42 @@
+-- Categories replace the internal/external flag. Existing rows move to the default `internal` and `external` categories.
+-- The column defaults only fill the existing rows, the bot always sets the category.
+ALTER TABLE suggestions ADD COLUMN category TEXT NOT NULL DEFAULT 'external';
+UPDATE suggestions SET category = 'internal' WHERE internal;
+ALTER TABLE suggestions DROP COLUMN internal;
+
+ALTER TABLE polls ADD COLUMN category TEXT NOT NULL DEFAULT 'external';
+UPDATE polls SET category = 'internal' WHERE internal;
+ALTER TABLE polls DROP COLUMN internal;
+
+ALTER TABLE deleted_suggestions ADD COLUMN category TEXT NOT NULL DEFAULT 'external';
+UPDATE deleted_suggestions SET category = 'internal' WHERE internal;
+ALTER TABLE deleted_suggestions DROP COLUMN internal;
+
+ALTER TABLE blocked_attempts ADD COLUMN category TEXT NOT NULL DEFAULT 'external';
+UPDATE blocked_attempts SET category = 'internal' WHERE internal;
+ALTER TABLE blocked_attempts DROP COLUMN internal;
+
+CREATE TABLE queue_skips_new (
+    category TEXT PRIMARY KEY
+);
+
+INSERT INTO queue_skips_new (category)
+SELECT CASE WHEN internal THEN 'internal' ELSE 'external' END
+FROM queue_skips;
+
+DROP TABLE queue_skips;
+
+ALTER TABLE queue_skips_new RENAME TO queue_skips;
+
+CREATE TABLE last_announcements_new (
+    category TEXT PRIMARY KEY,
+    timestamp DATETIME NOT NULL
+);
+
+INSERT INTO last_announcements_new (category, timestamp)
+SELECT CASE WHEN internal THEN 'internal' ELSE 'external' END, timestamp
+FROM last_announcements;
+
+DROP TABLE last_announcements;
+
+ALTER TABLE last_announcements_new RENAME TO last_announcements;
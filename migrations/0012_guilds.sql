@@ -0,0 +1,85 @@
+-- Every row belongs to a guild. Rows from before multi-guild support get the guild ID 0,
+-- which the bot replaces with the first configured guild on startup.
+ALTER TABLE suggestions ADD COLUMN guild_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE polls ADD COLUMN guild_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE deleted_suggestions ADD COLUMN guild_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE notices ADD COLUMN guild_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE blocked_attempts ADD COLUMN guild_id INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE blocked_users_new (
+    guild_id INTEGER NOT NULL DEFAULT 0,
+    user_id INTEGER NOT NULL,
+    reason TEXT,
+    timestamp DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+INSERT INTO blocked_users_new (user_id, reason, timestamp)
+SELECT user_id, reason, timestamp
+FROM blocked_users;
+
+DROP TABLE blocked_users;
+
+ALTER TABLE blocked_users_new RENAME TO blocked_users;
+
+CREATE TABLE blocked_artists_new (
+    guild_id INTEGER NOT NULL DEFAULT 0,
+    normalized_name TEXT NOT NULL,
+    artist_name TEXT NOT NULL,
+    reason TEXT,
+    timestamp DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (guild_id, normalized_name)
+);
+
+INSERT INTO blocked_artists_new (normalized_name, artist_name, reason, timestamp)
+SELECT normalized_name, artist_name, reason, timestamp
+FROM blocked_artists;
+
+DROP TABLE blocked_artists;
+
+ALTER TABLE blocked_artists_new RENAME TO blocked_artists;
+
+CREATE TABLE queue_skips_new (
+    guild_id INTEGER NOT NULL DEFAULT 0,
+    category TEXT NOT NULL,
+    PRIMARY KEY (guild_id, category)
+);
+
+INSERT INTO queue_skips_new (category)
+SELECT category
+FROM queue_skips;
+
+DROP TABLE queue_skips;
+
+ALTER TABLE queue_skips_new RENAME TO queue_skips;
+
+CREATE TABLE last_announcements_new (
+    guild_id INTEGER NOT NULL DEFAULT 0,
+    category TEXT NOT NULL,
+    timestamp DATETIME NOT NULL,
+    PRIMARY KEY (guild_id, category)
+);
+
+INSERT INTO last_announcements_new (category, timestamp)
+SELECT category, timestamp
+FROM last_announcements;
+
+DROP TABLE last_announcements;
+
+ALTER TABLE last_announcements_new RENAME TO last_announcements;
+
+CREATE TABLE announcement_slots_new (
+    guild_id INTEGER NOT NULL DEFAULT 0,
+    slot DATETIME NOT NULL,
+    status INTEGER NOT NULL,
+    timestamp DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (guild_id, slot)
+);
+
+INSERT INTO announcement_slots_new (slot, status, timestamp)
+SELECT slot, status, timestamp
+FROM announcement_slots;
+
+DROP TABLE announcement_slots;
+
+ALTER TABLE announcement_slots_new RENAME TO announcement_slots;
@@ -0,0 +1,528 @@
+use std::{env, fs, path::PathBuf};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
+use color_eyre::{
+    Result,
+    eyre::{Context as _, eyre},
+};
+use poise::serenity_prelude::{GuildId, Http};
+
+use crate::{
+    config::{Config, DataDir},
+    database,
+    export::{ExportFilter, ExportFormat, ExportStatus},
+    import::{ImportOptions, ImportTarget, import, parse_rows},
+    schedule::{Announcement, next_slot, plan_slot, project_announcements},
+    types::Data,
+    validate::check_configs,
+};
+
+/// The format of dates printed by the commands.
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M UTC";
+
+/// A Discord bot for artist feature automation.
+///
+/// Runs the bot if no command is given.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// The directory of the config, the database and the other files of the bot.
+    #[arg(
+        long,
+        env = "ARTISTIC_DATA_DIR",
+        default_value = "./data",
+        global = true
+    )]
+    pub data_dir: PathBuf,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Check the config against the live guilds and print every problem.
+    CheckConfig {
+        /// Only check what can be checked without connecting to Discord.
+        #[arg(long)]
+        offline: bool,
+    },
+
+    /// Inspect and maintain the database.
+    ///
+    /// `stats` and `export` only read the database and don't migrate it.
+    #[command(subcommand)]
+    Db(DbCommand),
+
+    /// Inspect the announcement queues without changing the database.
+    #[command(subcommand)]
+    Queue(QueueCommand),
+
+    /// Export the suggestions, polls and archived suggestions of every guild without changing the database.
+    Export {
+        /// The file format.
+        #[arg(long, value_enum, default_value_t)]
+        format: ExportFormat,
+
+        /// Only export rows of the category with this ID.
+        #[arg(long)]
+        category: Option<String>,
+
+        /// Only export rows with this status.
+        #[arg(long, value_enum)]
+        status: Option<ExportStatus>,
+
+        /// Only export rows submitted on or after this date (YYYY-MM-DD).
+        #[arg(long)]
+        since: Option<NaiveDate>,
+
+        /// Only export rows submitted on or before this date (YYYY-MM-DD).
+        #[arg(long)]
+        until: Option<NaiveDate>,
+
+        /// The directory to write the files to.
+        #[arg(long, default_value = ".")]
+        output: PathBuf,
+    },
+
+    /// Import suggestions from a CSV or JSON file straight into the approved queues.
+    ///
+    /// Like the bot, this migrates the database first.
+    /// Opening a poll for every suggestion needs the running bot, use `/import` for that.
+    Import {
+        /// The file to import, with the columns of the suggestions of an export.
+        path: PathBuf,
+
+        /// The category ID of rows without one.
+        #[arg(long)]
+        category: Option<String>,
+
+        /// The guild to import into. Required if several guilds are configured.
+        #[arg(long)]
+        guild: Option<GuildId>,
+
+        /// Print what would be imported without changing anything.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Import rows that look like duplicates too.
+        #[arg(long)]
+        keep_duplicates: bool,
+    },
+
+    /// Print what the bot will announce in the next slot of every guild, without posting anything.
+    ///
+    /// Posting is left to the running bot.
+    Announce,
+}
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// Print the number of suggestions and other rows of every guild.
+    Stats,
+
+    /// Write a copy of the database to a new file.
+    Export {
+        /// The file to write. It must not exist yet.
+        path: PathBuf,
+    },
+
+    /// Replace the database with a copy written by `db export`. The bot must not be running.
+    Import {
+        /// The file to import.
+        path: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum QueueCommand {
+    /// List the approved suggestions in the order they will be announced.
+    List {
+        /// The ID of the queue to list. Lists every queue if not given.
+        #[arg(long)]
+        category: Option<String>,
+    },
+}
+
+/// Runs a command.
+pub async fn run(command: Command, data_dir: &DataDir) -> Result<()> {
+    match command {
+        Command::CheckConfig { offline } => check_config(data_dir, offline).await,
+        Command::Db(DbCommand::Stats) => db_stats(data_dir).await,
+        Command::Db(DbCommand::Export { path }) => db_export(data_dir, path).await,
+        Command::Db(DbCommand::Import { path }) => db_import(data_dir, path).await,
+        Command::Queue(QueueCommand::List { category }) => queue_list(data_dir, category).await,
+        Command::Export {
+            format,
+            category,
+            status,
+            since,
+            until,
+            output,
+        } => {
+            let filter = ExportFilter {
+                category,
+                status,
+                since,
+                until,
+            };
+            export(data_dir, format, &filter, output).await
+        }
+        Command::Import {
+            path,
+            category,
+            guild,
+            dry_run,
+            keep_duplicates,
+        } => {
+            let options = ImportOptions {
+                category,
+                dry_run,
+                skip_duplicates: !keep_duplicates,
+            };
+            import_suggestions(data_dir, path, guild, &options).await
+        }
+        Command::Announce => announce_dry_run(data_dir).await,
+    }
+}
+
+/// Connects to the database and loads the data of every configured guild, like the bot does on startup.
+///
+/// The database is migrated and the rows from before multi-guild support are adopted by the first guild.
+async fn load(data_dir: &DataDir) -> Result<Vec<Data>> {
+    let pool = database::connect(data_dir).await?;
+    let configs = Config::load(data_dir)?;
+
+    // the rows from before multi-guild support belong to the first guild
+    database::adopt_legacy_rows(&pool, configs[0].guild).await?;
+
+    let mut guilds = Vec::new();
+
+    for config in configs {
+        guilds.push(Data::new(pool.clone(), config).await?);
+    }
+
+    Ok(guilds)
+}
+
+/// Opens the database read-only and loads the data of every configured guild, for commands that only inspect it.
+async fn inspect(data_dir: &DataDir) -> Result<Vec<Data>> {
+    let pool = database::open_read_only(data_dir).await?;
+    let mut guilds = Vec::new();
+
+    for config in Config::load(data_dir)? {
+        guilds.push(Data::new(pool.clone(), config).await?);
+    }
+
+    Ok(guilds)
+}
+
+/// Formats a date for the output of the commands.
+fn format_date(date: DateTime<Utc>) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
+
+/// Checks the config, against the live guilds unless `offline`, and prints the report.
+async fn check_config(data_dir: &DataDir, offline: bool) -> Result<()> {
+    let configs = Config::load(data_dir)?;
+
+    let report = if offline {
+        check_configs(None, &configs).await
+    } else {
+        let token = env::var("DISCORD_TOKEN").expect("environment variable DISCORD_TOKEN missing");
+        check_configs(Some(&Http::new(&token)), &configs).await
+    };
+
+    match report {
+        Some(report) => Err(eyre!(report)),
+        None => {
+            println!("The config of {} guild(s) is valid.", configs.len());
+            Ok(())
+        }
+    }
+}
+
+/// Prints the number of suggestions and other rows of every guild.
+async fn db_stats(data_dir: &DataDir) -> Result<()> {
+    for data in inspect(data_dir).await? {
+        let config = data.config();
+        let stats = data.fetch_stats().await?;
+
+        println!("guild {}:", data.guild);
+
+        for category in &stats.categories {
+            let name = config
+                .category(&category.category)
+                .map_or(category.category.as_str(), |config| config.name.as_str());
+
+            println!(
+                "  {name}: {} pending, {} queued, {} archived",
+                category.pending, category.queued, category.archived
+            );
+        }
+
+        println!(
+            "  blocklist: {} user(s), {} artist(s), {} rejected submission(s)",
+            stats.blocked_users, stats.blocked_artists, stats.blocked_attempts
+        );
+        println!("  undelivered notices: {}", stats.notices);
+        println!("  handled announcement slots: {}", stats.slots);
+    }
+
+    Ok(())
+}
+
+/// Writes a copy of the database to a new file.
+async fn db_export(data_dir: &DataDir, path: PathBuf) -> Result<()> {
+    if path.exists() {
+        return Err(eyre!("{} already exists", path.display()));
+    }
+
+    let pool = database::open_read_only(data_dir).await?;
+    database::backup(&pool, &path).await?;
+
+    println!("Exported the database to {}", path.display());
+
+    Ok(())
+}
+
+/// Replaces the database with a copy, keeping the old database as `database.sqlite.bak`.
+async fn db_import(data_dir: &DataDir, path: PathBuf) -> Result<()> {
+    let database = data_dir.database();
+    let staged = database.with_extension("sqlite.import");
+
+    fs::copy(&path, &staged).wrap_err_with(|| format!("failed to copy {}", path.display()))?;
+
+    // migrating the copy checks that it is a database of the bot and not of a newer version
+    match database::open(&staged).await {
+        Ok(pool) => pool.close().await,
+        Err(e) => {
+            fs::remove_file(&staged)
+                .wrap_err_with(|| format!("failed to remove {}", staged.display()))?;
+            return Err(e.wrap_err(format!("{} can't be imported", path.display())));
+        }
+    }
+
+    if database.exists() {
+        let backup = database.with_extension("sqlite.bak");
+
+        fs::rename(&database, &backup)
+            .wrap_err_with(|| format!("failed to move {}", database.display()))?;
+
+        println!("Moved the old database to {}", backup.display());
+    }
+
+    fs::rename(&staged, &database)
+        .wrap_err_with(|| format!("failed to move {}", staged.display()))?;
+
+    println!("Imported the database from {}", path.display());
+
+    Ok(())
+}
+
+/// Writes the export files of every guild to a directory.
+async fn export(
+    data_dir: &DataDir,
+    format: ExportFormat,
+    filter: &ExportFilter,
+    output: PathBuf,
+) -> Result<()> {
+    fs::create_dir_all(&output)
+        .wrap_err_with(|| format!("failed to create {}", output.display()))?;
+
+    for data in inspect(data_dir).await? {
+        let export = data.export(filter).await?;
+
+        for file in export.render(format, &format!("artistic-export-{}", data.guild))? {
+            let path = output.join(&file.name);
+
+            fs::write(&path, file.content)
+                .wrap_err_with(|| format!("failed to write {}", path.display()))?;
+
+            println!("Wrote {}", path.display());
+        }
+
+        println!("Exported {} row(s) of guild {}", export.len(), data.guild);
+    }
+
+    Ok(())
+}
+
+/// Imports suggestions from a file into the approved queues of a guild and prints the report.
+async fn import_suggestions(
+    data_dir: &DataDir,
+    path: PathBuf,
+    guild: Option<GuildId>,
+    options: &ImportOptions,
+) -> Result<()> {
+    let content = fs::read(&path).wrap_err_with(|| format!("failed to read {}", path.display()))?;
+    let rows = parse_rows(&path.to_string_lossy(), &content)?;
+
+    let mut guilds = load(data_dir).await?;
+    let data = match guild {
+        Some(guild) => guilds
+            .into_iter()
+            .find(|data| data.guild == guild)
+            .ok_or_else(|| eyre!("guild {guild} is not configured"))?,
+        None if guilds.len() == 1 => guilds.remove(0),
+        None => {
+            return Err(eyre!(
+                "several guilds are configured, choose one with --guild"
+            ));
+        }
+    };
+
+    let report = import(&data, rows, ImportTarget::Queue, options).await?;
+
+    for line in &report.lines {
+        println!("{line}");
+    }
+
+    println!("{}", report.summary());
+
+    Ok(())
+}
+
+/// Prints the approved suggestions of every guild in the order they will be announced.
+async fn queue_list(data_dir: &DataDir, category: Option<String>) -> Result<()> {
+    let now = Utc::now();
+
+    for data in inspect(data_dir).await? {
+        let config = data.config();
+        let categories = match &category {
+            Some(id) => vec![config.category(id)?],
+            None => config.categories.iter().collect(),
+        };
+
+        for category in categories {
+            let entries = data.fetch_queue(&category.id).await?;
+            let skip_next = data.fetch_queue_skip(&category.id).await?;
+            let last_announcement = data.fetch_last_announcement(&category.id).await?;
+            let dates = project_announcements(
+                now,
+                &config,
+                category,
+                last_announcement,
+                skip_next,
+                entries.len(),
+            );
+
+            println!("guild {}, {} queue:", data.guild, category.name);
+
+            if skip_next {
+                println!("  The next scheduled pick will be skipped.");
+            }
+
+            if entries.is_empty() {
+                println!("  The queue is empty.");
+            }
+
+            for (index, (entry, date)) in entries.iter().zip(dates).enumerate() {
+                let approved_at = entry.approved_at.map_or("unknown".into(), |approved_at| {
+                    approved_at.format(DATE_FORMAT).to_string()
+                });
+
+                println!(
+                    "  {}. {} by {} (#{}), submitted by {} ({}), approved {approved_at}, announced {}",
+                    index + 1,
+                    entry.album_name,
+                    entry.artist_name,
+                    entry.id,
+                    entry.username,
+                    entry.user_id,
+                    format_date(date)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the announcements the bot would post in the next slot of every guild, without changing anything.
+async fn announce_dry_run(data_dir: &DataDir) -> Result<()> {
+    for data in inspect(data_dir).await? {
+        let config = data.config();
+        let slot = next_slot(Utc::now(), &config);
+
+        println!("guild {}, slot {}:", data.guild, format_date(slot));
+
+        for (category, announcement) in plan_slot(&data, &config, slot).await {
+            let suggestion = match announcement? {
+                Announcement::NotDue => {
+                    println!("  {}: not due", category.name);
+                    continue;
+                }
+                Announcement::Skipped => {
+                    println!("  {}: skipped by a facilitator", category.name);
+                    continue;
+                }
+                Announcement::EmptyQueue => {
+                    println!("  {}: the queue is empty", category.name);
+
+                    if let Some(message) = &config.empty_queue_message {
+                        println!(
+                            "    in channel {}: {message}",
+                            category.announcement_channel
+                        );
+                    }
+
+                    continue;
+                }
+                Announcement::Post(suggestion) => suggestion,
+            };
+
+            println!(
+                "  {} in channel {}:",
+                category.name, category.announcement_channel
+            );
+
+            if let Some(ping_role) = config.ping_role(category) {
+                println!("    <@&{ping_role}>");
+            }
+
+            println!("    {}", category.announcement_title);
+            println!("    by {} ({})", suggestion.username, suggestion.user_id);
+
+            for (name, value, _) in suggestion.announcement_fields() {
+                println!("    {name}: {}", value.replace('\n', "\n      "));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_cli() {
+        const TESTS: &[(&[&str], bool)] = &[
+            (&["artistic"], true),
+            (&["artistic", "check-config", "--offline"], true),
+            (&["artistic", "announce"], true),
+            (&["artistic", "announce", "--dry-run"], false),
+            (
+                &["artistic", "queue", "list", "--data-dir", "/srv/artistic"],
+                true,
+            ),
+            (
+                &["artistic", "queue", "list", "--category", "external"],
+                true,
+            ),
+            (&["artistic", "db", "export", "backup.db"], true),
+            (&["artistic", "db", "export"], false),
+        ];
+
+        Cli::command().debug_assert();
+
+        for (args, valid) in TESTS {
+            assert_eq!(Cli::try_parse_from(*args).is_ok(), *valid, "{args:?}");
+        }
+    }
+}
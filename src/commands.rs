@@ -1,42 +1,154 @@
+use chrono::{NaiveDate, Utc};
 use color_eyre::{Report, Result, eyre::Context as _};
-use poise::{ApplicationContext, Command, command, serenity_prelude::*};
+use itertools::Itertools;
+use poise::{
+    ApplicationContext, ChoiceParameter, Command, CreateReply, builtins::paginate, command,
+    serenity_prelude::*,
+};
+use tracing::error;
 
-use crate::types::{Data, Suggestion};
+use crate::{
+    config::{Category, Config, DuplicatePolicy},
+    export::{ExportFilter, ExportFormat, ExportStatus},
+    import::{ImportOptions, ImportTarget, import, parse_rows},
+    links::LinkError,
+    schedule::project_announcements,
+    types::{BlockedAttempt, Bot, HistoryFilter, PollStatus, RecordedBlockedAttempt, Suggestion},
+};
+
+/// The number of entries shown per page in lists.
+const PAGE_SIZE: usize = 10;
+
+/// The response to submissions rejected because of the blocklist.
+pub const BLOCKED_MESSAGE: &str = "Sorry, this suggestion can't be accepted.";
+
+/// Suggests the categories whose ID or name contains the partial input.
+async fn autocomplete_category(
+    ctx: ApplicationContext<'_, Bot, Report>,
+    partial: &str,
+) -> Vec<AutocompleteChoice> {
+    let Ok(data) = ctx.data.guild(ctx.guild_id()) else {
+        return Vec::new();
+    };
+    let partial = partial.to_lowercase();
+
+    data.config()
+        .categories
+        .iter()
+        .filter(|category| {
+            category.id.to_lowercase().contains(&partial)
+                || category.name.to_lowercase().contains(&partial)
+        })
+        .map(|category| AutocompleteChoice::new(category.name.clone(), category.id.clone()))
+        .collect()
+}
+
+/// Returns the category with the given ID, telling the user if there is none.
+async fn find_category(
+    ctx: ApplicationContext<'_, Bot, Report>,
+    id: &str,
+) -> Result<Option<Category>> {
+    match ctx.data.guild(ctx.guild_id())?.config().category(id) {
+        Ok(category) => Ok(Some(category.clone())),
+        Err(_) => {
+            ctx.say(format!("There is no category `{id}`."))
+                .await
+                .wrap_err("failed to send response")?;
+            Ok(None)
+        }
+    }
+}
+
+/// Returns the name of the category with the given ID, or the ID if the category no longer exists.
+fn category_name<'a>(config: &'a Config, id: &'a str) -> &'a str {
+    config
+        .category(id)
+        .map_or(id, |category| category.name.as_str())
+}
+
+/// A final suggestion status as a command parameter.
+#[derive(Debug, Clone, Copy, ChoiceParameter)]
+enum FinalStatus {
+    #[name = "featured"]
+    Featured,
+    #[name = "revoked"]
+    Revoked,
+    #[name = "vetoed"]
+    Vetoed,
+    #[name = "expired"]
+    Expired,
+}
+
+impl FinalStatus {
+    fn poll_status(self) -> PollStatus {
+        match self {
+            FinalStatus::Featured => PollStatus::Completed,
+            FinalStatus::Revoked => PollStatus::Revoked,
+            FinalStatus::Vetoed => PollStatus::Vetoed,
+            FinalStatus::Expired => PollStatus::Expired,
+        }
+    }
+}
+
+/// Where a restored or imported suggestion goes.
+#[derive(Debug, Clone, Copy, ChoiceParameter)]
+enum Destination {
+    #[name = "queue"]
+    Queue,
+    #[name = "poll"]
+    Poll,
+}
 
 /// Suggest an artist to be featured.
 #[command(slash_command, guild_only, ephemeral)]
 async fn suggest(
-    ctx: ApplicationContext<'_, Data, Report>,
+    ctx: ApplicationContext<'_, Bot, Report>,
 
-    #[description = "Artists in the C418 community are `internal`. All other artists are `external`."]
-    #[choices("internal", "external")]
-    artist: &'static str,
+    #[description = "The category to suggest an artist for"]
+    #[autocomplete = "autocomplete_category"]
+    category: String,
 ) -> Result<()> {
-    let response = ctx.interaction
+    let data = ctx.data.guild(ctx.guild_id())?;
+    let config = data.config();
+    let Some(category) = find_category(ctx, &category).await? else {
+        return Ok(());
+    };
+
+    // the modal has to be the first response, so nothing else is done before it but local checks
+    if data.is_user_blocked(ctx.author().id).await? {
+        ctx.say(BLOCKED_MESSAGE)
+            .await
+            .wrap_err("failed to send response")?;
+
+        data.record_blocked_attempt(&BlockedAttempt {
+            user_id: ctx.author().id,
+            username: ctx.author().name.clone(),
+            artist_name: None,
+            album_name: None,
+            category: category.id.clone(),
+        })
+        .await?;
+        return Ok(());
+    }
+
+    if !is_facilitator_member(ctx.interaction.member.as_deref(), &config)
+        && let Some(reason) = data
+            .check_submission_limits(ctx.author().id, &category)
+            .await?
+    {
+        ctx.say(reason).await.wrap_err("failed to send response")?;
+        return Ok(());
+    }
+
+    let response = ctx
+        .interaction
         .quick_modal(
             ctx.serenity_context,
-            CreateQuickModal::new(format!("Suggest an {artist} artist!"))
-                .field(
-                    CreateInputText::new(InputTextStyle::Short, "Artist name", "")
-                        .placeholder("The artist name")
-                        .max_length(256),
-                )
-                .field(
-                    CreateInputText::new(InputTextStyle::Short, "Album name", "")
-                        .placeholder("The album name")
-                        .max_length(256),
-                )
-                .field(
-                    CreateInputText::new(InputTextStyle::Paragraph, "Links", "")
-                        .placeholder("One or more links to the album on any platform.\nEach link should be on a new line.")
-                        .max_length(1024)
-                )
-                .field(
-                    CreateInputText::new(InputTextStyle::Paragraph, "Notes", "")
-                        .placeholder("Any additional notes")
-                        .max_length(1024)
-                        .required(false),
-                ).timeout(ctx.data.config.form_timeout),
+            Suggestion::modal(
+                format!("{} Suggestion", category.name),
+                config.form_timeout,
+                None,
+            ),
         )
         .await?;
 
@@ -44,52 +156,209 @@ async fn suggest(
         return Ok(());
     };
 
+    // defer the response so that every outcome can be reported by editing it
+    response
+        .interaction
+        .defer_ephemeral(&ctx)
+        .await
+        .wrap_err("failed to defer response")?;
+
+    let result = submit_suggestion(ctx, &response, &category).await;
+
+    // notices are sent after the response, because the first follow-up of a deferred response replaces it
+    for notice in data.take_notices(ctx.author().id).await? {
+        response
+            .interaction
+            .create_followup(
+                &ctx,
+                CreateInteractionResponseFollowup::new()
+                    .content(notice)
+                    .ephemeral(true),
+            )
+            .await
+            .wrap_err("failed to send notice")?;
+    }
+
+    result
+}
+
+/// Validates a submitted suggestion and creates its poll, reporting the outcome in the deferred response.
+async fn submit_suggestion(
+    ctx: ApplicationContext<'_, Bot, Report>,
+    response: &QuickModalResponse,
+    category: &Category,
+) -> Result<()> {
+    let data = ctx.data.guild(ctx.guild_id())?;
+    let config = data.config();
     let respond_with_error = async {
         response
             .interaction
-            .create_response(
+            .edit_response(
                 &ctx,
-                CreateInteractionResponse::Message(
-                    CreateInteractionResponseMessage::new()
-                        .content("There was an error processing your submission.")
-                        .ephemeral(true),
-                ),
+                EditInteractionResponse::new()
+                    .content("There was an error processing your submission.")
+                    .components(vec![]),
             )
             .await
-            .wrap_err("failed to send response")
+            .wrap_err("failed to edit response")
     };
 
     // parse the response
-    let suggestion = match Suggestion::parse_response(&response, artist == "internal")
-        .wrap_err("failed to parse form response")
-    {
-        Ok(suggestion) => suggestion,
+    let suggestion =
+        match Suggestion::parse_response(response, &category.id, &config.link_host_denylist) {
+            Ok(suggestion) => suggestion,
+            Err(e) => {
+                // invalid links are the submitter's mistake, so they are told what went wrong
+                if let Some(e) = e.downcast_ref::<LinkError>() {
+                    response
+                        .interaction
+                        .edit_response(&ctx, EditInteractionResponse::new().content(e.to_string()))
+                        .await
+                        .wrap_err("failed to edit response")?;
+                    return Ok(());
+                }
+
+                respond_with_error.await?;
+                return Err(e.wrap_err("failed to parse form response"));
+            }
+        };
+
+    // check the blocklist
+    let blocked = match data.is_artist_blocked(&suggestion.artist_name).await {
+        Ok(blocked) => blocked,
         Err(e) => {
             respond_with_error.await?;
             return Err(e);
         }
     };
 
-    // create the poll
-    let poll_id = match ctx
-        .data
-        .create_poll(ctx.serenity_context, &suggestion)
+    if blocked {
+        data.record_blocked_attempt(&BlockedAttempt {
+            user_id: suggestion.user_id,
+            username: suggestion.username.clone(),
+            artist_name: Some(suggestion.artist_name.clone()),
+            album_name: Some(suggestion.album_name.clone()),
+            category: suggestion.category.clone(),
+        })
+        .await?;
+
+        response
+            .interaction
+            .edit_response(
+                &ctx,
+                EditInteractionResponse::new().content(BLOCKED_MESSAGE),
+            )
+            .await
+            .wrap_err("failed to edit response")?;
+        return Ok(());
+    }
+
+    // check for duplicates
+    let duplicates = match data
+        .find_duplicates(&suggestion)
         .await
-        .wrap_err("failed to create poll")
+        .wrap_err("failed to find duplicates")
     {
-        Ok(poll_id) => poll_id,
+        Ok(duplicates) => duplicates,
         Err(e) => {
             respond_with_error.await?;
             return Err(e);
         }
     };
 
-    // add the suggestion to the database
-    if let Err(e) = ctx
-        .data
-        .insert_suggestion(&suggestion, poll_id)
+    if !duplicates.is_empty() {
+        let duplicates = data.format_duplicates(&duplicates);
+
+        if config.duplicate_policy == DuplicatePolicy::Block {
+            response
+                .interaction
+                .edit_response(
+                    &ctx,
+                    EditInteractionResponse::new().content(format!(
+                        "This suggestion looks like a duplicate of:\n{duplicates}\n\nYour submission was not accepted."
+                    )),
+                )
+                .await
+                .wrap_err("failed to edit response")?;
+            return Ok(());
+        }
+
+        // ask the submitter to confirm the submission
+        let submit_id = format!("{}:submit", response.interaction.id);
+        let cancel_id = format!("{}:cancel", response.interaction.id);
+
+        response
+            .interaction
+            .edit_response(
+                &ctx,
+                EditInteractionResponse::new()
+                    .content(format!(
+                        "This suggestion looks like a duplicate of:\n{duplicates}\n\nDo you want to submit it anyway?"
+                    ))
+                    .components(vec![CreateActionRow::Buttons(vec![
+                        CreateButton::new(&submit_id)
+                            .label("Submit anyway")
+                            .style(ButtonStyle::Primary),
+                        CreateButton::new(&cancel_id)
+                            .label("Cancel")
+                            .style(ButtonStyle::Secondary),
+                    ])]),
+            )
+            .await
+            .wrap_err("failed to edit response")?;
+
+        let confirmation = ComponentInteractionCollector::new(ctx.serenity_context)
+            .author_id(response.interaction.user.id)
+            .filter({
+                let (submit_id, cancel_id) = (submit_id.clone(), cancel_id.clone());
+                move |interaction| {
+                    interaction.data.custom_id == submit_id
+                        || interaction.data.custom_id == cancel_id
+                }
+            })
+            .timeout(config.form_timeout)
+            .next()
+            .await;
+
+        let Some(confirmation) = confirmation else {
+            response
+                .interaction
+                .edit_response(
+                    &ctx,
+                    EditInteractionResponse::new()
+                        .content("Your submission timed out.")
+                        .components(vec![]),
+                )
+                .await
+                .wrap_err("failed to edit response")?;
+            return Ok(());
+        };
+
+        if confirmation.data.custom_id != submit_id {
+            confirmation
+                .create_response(
+                    &ctx,
+                    CreateInteractionResponse::UpdateMessage(
+                        CreateInteractionResponseMessage::new()
+                            .content("Your submission was cancelled.")
+                            .components(vec![]),
+                    ),
+                )
+                .await
+                .wrap_err("failed to send response")?;
+            return Ok(());
+        }
+
+        confirmation
+            .create_response(&ctx, CreateInteractionResponse::Acknowledge)
+            .await
+            .wrap_err("failed to send response")?;
+    }
+
+    // create the poll and add the suggestion to the database
+    if let Err(e) = data
+        .open_poll(ctx.serenity_context, &suggestion, true)
         .await
-        .wrap_err("failed to insert suggestion")
     {
         respond_with_error.await?;
         return Err(e);
@@ -98,20 +367,777 @@ async fn suggest(
     // respond to the submission
     response
         .interaction
-        .create_response(
+        .edit_response(
             &ctx,
-            CreateInteractionResponse::Message(
-                CreateInteractionResponseMessage::new()
-                    .content("Thanks for your suggestion!")
-                    .ephemeral(true),
-            ),
+            EditInteractionResponse::new()
+                .content("Thanks for your suggestion!")
+                .components(vec![]),
+        )
+        .await
+        .wrap_err("failed to edit response")?;
+
+    Ok(())
+}
+
+/// Checks if the author is a poll facilitator and tells them if they aren't.
+async fn is_facilitator(ctx: poise::Context<'_, Bot, Report>) -> Result<bool> {
+    let config = ctx.data().guild(ctx.guild_id())?.config();
+    let is_facilitator = is_facilitator_member(ctx.author_member().await.as_deref(), &config);
+
+    if !is_facilitator {
+        ctx.send(
+            CreateReply::default()
+                .content("Only designated facilitators can use this command!")
+                .ephemeral(true),
+        )
+        .await
+        .wrap_err("failed to send response")?;
+    }
+
+    Ok(is_facilitator)
+}
+
+/// Returns whether a member has the facilitator role, without responding to them.
+///
+/// Guild interactions come with the roles of their member, so no request is needed.
+fn is_facilitator_member(member: Option<&Member>, config: &Config) -> bool {
+    member.is_some_and(|member| member.roles.contains(&config.facilitator_role))
+}
+
+/// Manage the announcement queue.
+#[command(
+    slash_command,
+    guild_only,
+    ephemeral,
+    subcommands(
+        "queue_list",
+        "queue_move",
+        "queue_pin",
+        "queue_push_back",
+        "queue_skip"
+    ),
+    subcommand_required,
+    check = "is_facilitator"
+)]
+async fn queue(_ctx: ApplicationContext<'_, Bot, Report>) -> Result<()> {
+    Ok(())
+}
+
+/// List the approved suggestions in the order they will be announced.
+#[command(slash_command, guild_only, ephemeral, rename = "list")]
+async fn queue_list(
+    ctx: ApplicationContext<'_, Bot, Report>,
+
+    #[description = "The queue to list. Lists every queue if not given."]
+    #[autocomplete = "autocomplete_category"]
+    category: Option<String>,
+) -> Result<()> {
+    let data = ctx.data.guild(ctx.guild_id())?;
+    let config = data.config();
+    let categories = match category {
+        Some(category) => match find_category(ctx, &category).await? {
+            Some(category) => vec![category],
+            None => return Ok(()),
+        },
+        None => config.categories.clone(),
+    };
+
+    let now = Utc::now();
+    let mut pages = Vec::new();
+
+    for category in &categories {
+        let entries = data.fetch_queue(&category.id).await?;
+        let skip_next = data.fetch_queue_skip(&category.id).await?;
+        let last_announcement = data.fetch_last_announcement(&category.id).await?;
+        let dates = project_announcements(
+            now,
+            &config,
+            category,
+            last_announcement,
+            skip_next,
+            entries.len(),
+        );
+
+        let lines = entries
+            .iter()
+            .zip(dates)
+            .enumerate()
+            .map(|(index, (entry, date))| {
+                let approved_at = entry.approved_at.map_or("unknown".into(), |approved_at| {
+                    format!("<t:{}:d>", approved_at.and_utc().timestamp())
+                });
+
+                format!(
+                    "**{}.** {} by {} (#{})\nSubmitted by {} (<@{}>), approved {approved_at}, announced <t:{}:D>",
+                    index + 1,
+                    entry.album_name,
+                    entry.artist_name,
+                    entry.id,
+                    entry.username,
+                    entry.user_id,
+                    date.timestamp()
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut title = format!("**{} Queue**", category.name);
+
+        if skip_next {
+            title += "\nThe next scheduled pick will be skipped.";
+        }
+
+        if lines.is_empty() {
+            pages.push(format!("{title}\nThe queue is empty."));
+            continue;
+        }
+
+        let page_count = lines.len().div_ceil(PAGE_SIZE);
+
+        for (page, lines) in lines.chunks(PAGE_SIZE).enumerate() {
+            pages.push(format!(
+                "{title}\nPage {}/{page_count}\n\n{}",
+                page + 1,
+                lines.join("\n\n")
+            ));
+        }
+    }
+
+    let pages = pages.iter().map(String::as_str).collect::<Vec<_>>();
+
+    paginate(ctx.into(), &pages)
+        .await
+        .wrap_err("failed to paginate queue")?;
+
+    Ok(())
+}
+
+/// Moves a queued suggestion to a position in its queue, or to the back, and tells the facilitator.
+async fn move_in_queue(
+    ctx: ApplicationContext<'_, Bot, Report>,
+    suggestion_id: u64,
+    position: Option<usize>,
+) -> Result<()> {
+    let data = ctx.data.guild(ctx.guild_id())?;
+    let content = match data.move_in_queue(suggestion_id, position).await? {
+        Some(position) => format!(
+            "Moved suggestion #{suggestion_id} to position {}.",
+            position + 1
+        ),
+        None => format!("Suggestion #{suggestion_id} is not in a queue."),
+    };
+
+    ctx.say(content).await.wrap_err("failed to send response")?;
+
+    Ok(())
+}
+
+/// Move a queued suggestion to a position in its queue.
+#[command(slash_command, guild_only, ephemeral, rename = "move")]
+async fn queue_move(
+    ctx: ApplicationContext<'_, Bot, Report>,
+
+    #[description = "The suggestion ID, as shown in `/queue list`"] id: u64,
+
+    #[description = "The new position, starting at 1"]
+    #[min = 1]
+    position: usize,
+) -> Result<()> {
+    move_in_queue(ctx, id, Some(position - 1)).await
+}
+
+/// Move a queued suggestion to the front of its queue.
+#[command(slash_command, guild_only, ephemeral, rename = "pin")]
+async fn queue_pin(
+    ctx: ApplicationContext<'_, Bot, Report>,
+
+    #[description = "The suggestion ID, as shown in `/queue list`"] id: u64,
+) -> Result<()> {
+    move_in_queue(ctx, id, Some(0)).await
+}
+
+/// Move a queued suggestion to the back of its queue.
+#[command(slash_command, guild_only, ephemeral, rename = "push-back")]
+async fn queue_push_back(
+    ctx: ApplicationContext<'_, Bot, Report>,
+
+    #[description = "The suggestion ID, as shown in `/queue list`"] id: u64,
+) -> Result<()> {
+    move_in_queue(ctx, id, None).await
+}
+
+/// Skip the next scheduled pick of a queue once.
+#[command(slash_command, guild_only, ephemeral, rename = "skip")]
+async fn queue_skip(
+    ctx: ApplicationContext<'_, Bot, Report>,
+
+    #[description = "The queue to skip"]
+    #[autocomplete = "autocomplete_category"]
+    category: String,
+
+    #[description = "Cancel a previously requested skip instead"] cancel: Option<bool>,
+) -> Result<()> {
+    let data = ctx.data.guild(ctx.guild_id())?;
+    let Some(category) = find_category(ctx, &category).await? else {
+        return Ok(());
+    };
+
+    let skip = !cancel.unwrap_or(false);
+    data.set_queue_skip(&category.id, skip).await?;
+
+    let content = if skip {
+        format!("The next {} announcement will be skipped.", category.name)
+    } else {
+        format!(
+            "The next {} announcement will no longer be skipped.",
+            category.name
         )
+    };
+
+    ctx.say(content).await.wrap_err("failed to send response")?;
+
+    Ok(())
+}
+
+/// Manage the users and artists that may not be suggested.
+#[command(
+    slash_command,
+    guild_only,
+    ephemeral,
+    subcommands("blocklist_add", "blocklist_remove", "blocklist_list"),
+    subcommand_required,
+    check = "is_facilitator"
+)]
+async fn blocklist(_ctx: ApplicationContext<'_, Bot, Report>) -> Result<()> {
+    Ok(())
+}
+
+/// Block a user from suggesting or an artist from being suggested.
+#[command(slash_command, guild_only, ephemeral, rename = "add")]
+async fn blocklist_add(
+    ctx: ApplicationContext<'_, Bot, Report>,
+
+    #[description = "The user to block"] user: Option<User>,
+
+    #[description = "The artist to block"] artist: Option<String>,
+
+    #[description = "Why they are blocked, visible to facilitators"] reason: Option<String>,
+) -> Result<()> {
+    let data = ctx.data.guild(ctx.guild_id())?;
+    let mut lines = Vec::new();
+
+    if let Some(user) = user {
+        lines.push(if data.block_user(user.id, reason.as_deref()).await? {
+            format!("Blocked <@{}>.", user.id)
+        } else {
+            format!("<@{}> is already blocked.", user.id)
+        });
+    }
+
+    if let Some(artist) = artist {
+        lines.push(if data.block_artist(&artist, reason.as_deref()).await? {
+            format!("Blocked **{artist}**.")
+        } else {
+            format!("**{artist}** is already blocked.")
+        });
+    }
+
+    if lines.is_empty() {
+        lines.push("Please give a user or an artist to block.".into());
+    }
+
+    ctx.say(lines.join("\n"))
         .await
         .wrap_err("failed to send response")?;
 
     Ok(())
 }
 
-pub fn get() -> Vec<Command<Data, Report>> {
-    vec![suggest()]
+/// Unblock a user or an artist.
+#[command(slash_command, guild_only, ephemeral, rename = "remove")]
+async fn blocklist_remove(
+    ctx: ApplicationContext<'_, Bot, Report>,
+
+    #[description = "The user to unblock"] user: Option<User>,
+
+    #[description = "The artist to unblock"] artist: Option<String>,
+) -> Result<()> {
+    let data = ctx.data.guild(ctx.guild_id())?;
+    let mut lines = Vec::new();
+
+    if let Some(user) = user {
+        lines.push(if data.unblock_user(user.id).await? {
+            format!("Unblocked <@{}>.", user.id)
+        } else {
+            format!("<@{}> is not blocked.", user.id)
+        });
+    }
+
+    if let Some(artist) = artist {
+        lines.push(if data.unblock_artist(&artist).await? {
+            format!("Unblocked **{artist}**.")
+        } else {
+            format!("**{artist}** is not blocked.")
+        });
+    }
+
+    if lines.is_empty() {
+        lines.push("Please give a user or an artist to unblock.".into());
+    }
+
+    ctx.say(lines.join("\n"))
+        .await
+        .wrap_err("failed to send response")?;
+
+    Ok(())
+}
+
+/// List the blocked users and artists and the rejected submissions.
+#[command(slash_command, guild_only, ephemeral, rename = "list")]
+async fn blocklist_list(ctx: ApplicationContext<'_, Bot, Report>) -> Result<()> {
+    let data = ctx.data.guild(ctx.guild_id())?;
+    let config = data.config();
+    let reason = |reason: &Option<String>| {
+        reason
+            .as_ref()
+            .map_or(String::new(), |reason| format!(": {reason}"))
+    };
+
+    let users = data
+        .fetch_blocked_users()
+        .await?
+        .iter()
+        .map(|user| {
+            format!(
+                "<@{}>, blocked <t:{}:d>{}",
+                user.user_id,
+                user.timestamp.and_utc().timestamp(),
+                reason(&user.reason)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let artists = data
+        .fetch_blocked_artists()
+        .await?
+        .iter()
+        .map(|artist| {
+            format!(
+                "**{}**, blocked <t:{}:d>{}",
+                artist.artist_name,
+                artist.timestamp.and_utc().timestamp(),
+                reason(&artist.reason)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let attempts = data
+        .fetch_blocked_attempts()
+        .await?
+        .iter()
+        .map(|RecordedBlockedAttempt { attempt, timestamp }| {
+            let suggestion = match (&attempt.album_name, &attempt.artist_name) {
+                (Some(album_name), Some(artist_name)) => {
+                    format!("**{album_name}** by **{artist_name}**")
+                }
+                _ => "a suggestion".into(),
+            };
+
+            format!(
+                "{} (<@{}>) tried to suggest {suggestion} ({}) <t:{}:f>",
+                attempt.username,
+                attempt.user_id,
+                category_name(&config, &attempt.category),
+                timestamp.and_utc().timestamp()
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut pages = Vec::new();
+
+    for (title, lines) in [
+        ("Blocked Users", users),
+        ("Blocked Artists", artists),
+        ("Blocked Attempts", attempts),
+    ] {
+        if lines.is_empty() {
+            pages.push(format!("**{title}**\nNone."));
+            continue;
+        }
+
+        let page_count = lines.len().div_ceil(PAGE_SIZE);
+
+        for (page, lines) in lines.chunks(PAGE_SIZE).enumerate() {
+            pages.push(format!(
+                "**{title}**\nPage {}/{page_count}\n\n{}",
+                page + 1,
+                lines.join("\n")
+            ));
+        }
+    }
+
+    let pages = pages.iter().map(String::as_str).collect::<Vec<_>>();
+
+    paginate(ctx.into(), &pages)
+        .await
+        .wrap_err("failed to paginate blocklist")?;
+
+    Ok(())
+}
+
+/// Search the featured, revoked, vetoed and expired suggestions.
+#[command(slash_command, guild_only, ephemeral)]
+async fn history(
+    ctx: ApplicationContext<'_, Bot, Report>,
+
+    #[description = "Words to search for in artist, album and submitter names"] search: Option<
+        String,
+    >,
+
+    #[description = "Only show suggestions of this category"]
+    #[autocomplete = "autocomplete_category"]
+    category: Option<String>,
+
+    #[description = "Only show suggestions with this final status"] status: Option<FinalStatus>,
+
+    #[description = "Only show suggestions by this user"] submitter: Option<User>,
+) -> Result<()> {
+    let data = ctx.data.guild(ctx.guild_id())?;
+    let config = data.config();
+    let filter = HistoryFilter {
+        text: search,
+        category,
+        status: status.map(FinalStatus::poll_status),
+        user_id: submitter.map(|user| user.id),
+    };
+
+    let entries = data.search_history(&filter).await?;
+
+    if entries.is_empty() {
+        ctx.say("No suggestions found.")
+            .await
+            .wrap_err("failed to send response")?;
+        return Ok(());
+    }
+
+    let lines = entries
+        .iter()
+        .map(|entry| {
+            let suggestion = &entry.suggestion;
+            let status = match entry.status {
+                PollStatus::Completed => "featured",
+                PollStatus::Revoked => "revoked",
+                PollStatus::Vetoed => "vetoed",
+                PollStatus::Expired => "expired",
+                PollStatus::Pending { .. } => "archived",
+            };
+
+            let mut line = format!(
+                "**#{}** {} by {} ({})\nSubmitted by {} (<@{}>) <t:{}:D>",
+                suggestion.id,
+                suggestion.album_name,
+                suggestion.artist_name,
+                category_name(&config, &suggestion.category),
+                suggestion.username,
+                suggestion.user_id,
+                entry.timestamp.and_utc().timestamp()
+            );
+
+            match entry.archived_at {
+                Some(archived_at) => {
+                    line += &format!(", {status} <t:{}:D>", archived_at.and_utc().timestamp())
+                }
+                None => line += &format!(", {status}"),
+            }
+
+            line
+        })
+        .collect::<Vec<_>>();
+
+    let page_count = lines.len().div_ceil(PAGE_SIZE);
+
+    let pages = lines
+        .chunks(PAGE_SIZE)
+        .enumerate()
+        .map(|(page, lines)| {
+            format!(
+                "**History** ({} results)\nPage {}/{page_count}\n\n{}",
+                entries.len(),
+                page + 1,
+                lines.join("\n\n")
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let pages = pages.iter().map(String::as_str).collect::<Vec<_>>();
+
+    paginate(ctx.into(), &pages)
+        .await
+        .wrap_err("failed to paginate history")?;
+
+    Ok(())
+}
+
+/// Restore an archived suggestion.
+#[command(slash_command, guild_only, ephemeral, check = "is_facilitator")]
+async fn restore(
+    ctx: ApplicationContext<'_, Bot, Report>,
+
+    #[description = "The archived suggestion ID, as shown in `/history`"] id: u64,
+
+    #[description = "Put the suggestion straight into the queue or open a new poll for it"]
+    mode: Destination,
+) -> Result<()> {
+    let data = ctx.data.guild(ctx.guild_id())?;
+    let Some(archived) = data.fetch_archived_suggestion(id).await? else {
+        ctx.say(format!("There is no archived suggestion #{id}."))
+            .await
+            .wrap_err("failed to send response")?;
+        return Ok(());
+    };
+
+    if matches!(archived.status, PollStatus::Completed) {
+        ctx.say(format!("Suggestion #{id} has already been featured."))
+            .await
+            .wrap_err("failed to send response")?;
+        return Ok(());
+    }
+
+    let suggestion = &archived.suggestion;
+    let submitted = archived.timestamp.and_utc().timestamp();
+
+    let Some(category) = find_category(ctx, &suggestion.category).await? else {
+        return Ok(());
+    };
+
+    let mut content = match mode {
+        Destination::Queue => {
+            let suggestion_id = data.restore_to_queue(id).await?;
+
+            format!(
+                "Restored **{}** by **{}** (submitted <t:{submitted}:d>) to the {} queue as #{suggestion_id}.",
+                suggestion.album_name, suggestion.artist_name, category.name
+            )
+        }
+        Destination::Poll => {
+            ctx.defer_ephemeral()
+                .await
+                .wrap_err("failed to defer response")?;
+
+            data.restore_to_poll(ctx.serenity_context, &archived)
+                .await?;
+
+            format!(
+                "Opened a new poll for **{}** by **{}** (submitted <t:{submitted}:d>).",
+                suggestion.album_name, suggestion.artist_name
+            )
+        }
+    };
+
+    if let Some(reason) = &archived.reason {
+        content += &format!("\nIt was vetoed with the reason: {reason}");
+    }
+
+    ctx.say(content).await.wrap_err("failed to send response")?;
+
+    Ok(())
+}
+
+/// Parses a date given as `YYYY-MM-DD`, or returns the response to an invalid one.
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("`{date}` is not a date in the YYYY-MM-DD format."))
+}
+
+/// Export the suggestions, polls and archived suggestions as files.
+#[command(slash_command, guild_only, ephemeral, check = "is_facilitator")]
+async fn export(
+    ctx: ApplicationContext<'_, Bot, Report>,
+
+    #[description = "The file format, JSON if not given"] format: Option<ExportFormat>,
+
+    #[description = "Only export rows of this category"]
+    #[autocomplete = "autocomplete_category"]
+    category: Option<String>,
+
+    #[description = "Only export rows with this status"] status: Option<ExportStatus>,
+
+    #[description = "Only export rows submitted on or after this date (YYYY-MM-DD)"] since: Option<
+        String,
+    >,
+
+    #[description = "Only export rows submitted on or before this date (YYYY-MM-DD)"] until: Option<
+        String,
+    >,
+) -> Result<()> {
+    let data = ctx.data.guild(ctx.guild_id())?;
+
+    let (since, until) = match (
+        since.as_deref().map(parse_date).transpose(),
+        until.as_deref().map(parse_date).transpose(),
+    ) {
+        (Ok(since), Ok(until)) => (since, until),
+        (since, until) => {
+            let problems = [since.err(), until.err()].into_iter().flatten().join("\n");
+            ctx.say(problems)
+                .await
+                .wrap_err("failed to send response")?;
+            return Ok(());
+        }
+    };
+
+    ctx.defer_ephemeral()
+        .await
+        .wrap_err("failed to defer response")?;
+
+    let filter = ExportFilter {
+        category,
+        status,
+        since,
+        until,
+    };
+    let export = data.export(&filter).await?;
+    let files = export.render(
+        format.unwrap_or_default(),
+        &format!("artistic-export-{}", data.guild),
+    )?;
+
+    let mut reply = CreateReply::default().content(format!("Exported {} row(s).", export.len()));
+
+    for file in files {
+        reply = reply.attachment(CreateAttachment::bytes(file.content, file.name));
+    }
+
+    ctx.send(reply).await.wrap_err("failed to send response")?;
+
+    Ok(())
+}
+
+/// Import suggestions from a CSV or JSON file, such as the suggestions of an export.
+#[command(
+    slash_command,
+    guild_only,
+    ephemeral,
+    rename = "import",
+    check = "is_facilitator"
+)]
+async fn import_suggestions(
+    ctx: ApplicationContext<'_, Bot, Report>,
+
+    #[description = "The CSV or JSON file"] file: Attachment,
+
+    #[description = "Put the suggestions straight into the queue or open a poll for each"]
+    mode: Destination,
+
+    #[description = "The category of rows without one"]
+    #[autocomplete = "autocomplete_category"]
+    category: Option<String>,
+
+    #[description = "Only report what would be imported"] dry_run: Option<bool>,
+
+    #[description = "Skip suggestions that look like duplicates, true if not given"]
+    skip_duplicates: Option<bool>,
+) -> Result<()> {
+    let data = ctx.data.guild(ctx.guild_id())?;
+
+    if let Some(category) = &category
+        && find_category(ctx, category).await?.is_none()
+    {
+        return Ok(());
+    }
+
+    ctx.defer_ephemeral()
+        .await
+        .wrap_err("failed to defer response")?;
+
+    let content = file.download().await.wrap_err("failed to download file")?;
+    let rows = match parse_rows(&file.filename, &content) {
+        Ok(rows) => rows,
+        Err(e) => {
+            ctx.say(format!("The file can't be imported: {e:#}"))
+                .await
+                .wrap_err("failed to send response")?;
+            return Ok(());
+        }
+    };
+
+    let target = match mode {
+        Destination::Queue => ImportTarget::Queue,
+        Destination::Poll => ImportTarget::Poll(ctx.serenity_context),
+    };
+    let options = ImportOptions {
+        category,
+        dry_run: dry_run.unwrap_or(false),
+        skip_duplicates: skip_duplicates.unwrap_or(true),
+    };
+    let report = import(data, rows, target, &options).await?;
+
+    let mut reply = CreateReply::default().content(report.summary());
+
+    if !report.lines.is_empty() {
+        reply = reply.attachment(CreateAttachment::bytes(
+            report.lines.join("\n"),
+            "artistic-import-report.txt",
+        ));
+    }
+
+    ctx.send(reply).await.wrap_err("failed to send response")?;
+
+    Ok(())
+}
+
+/// Manage the bot configuration.
+#[command(
+    slash_command,
+    guild_only,
+    ephemeral,
+    subcommands("config_reload"),
+    subcommand_required,
+    check = "is_facilitator"
+)]
+async fn config(_ctx: ApplicationContext<'_, Bot, Report>) -> Result<()> {
+    Ok(())
+}
+
+/// Reload the configuration of this server without restarting the bot.
+#[command(slash_command, guild_only, ephemeral, rename = "reload")]
+async fn config_reload(ctx: ApplicationContext<'_, Bot, Report>) -> Result<()> {
+    let content = match ctx.data.reload_config(ctx.guild_id()) {
+        Ok(changes) if changes.is_empty() => "Reloaded the config. Nothing changed.".to_string(),
+        Ok(changes) => {
+            let mut content = "Reloaded the config:".to_string();
+
+            for change in &changes {
+                // messages are limited to 2000 characters
+                if content.len() + change.len() > 1900 {
+                    content += "\n…and more, see the logs.";
+                    break;
+                }
+
+                content += &format!("\n`{change}`");
+            }
+
+            content
+        }
+        Err(e) => {
+            error!("Failed to reload the config: {e:#}");
+            format!("The config was not reloaded: {e:#}")
+        }
+    };
+
+    ctx.say(content).await.wrap_err("failed to send response")?;
+
+    Ok(())
+}
+
+pub fn get() -> Vec<Command<Bot, Report>> {
+    vec![
+        suggest(),
+        queue(),
+        history(),
+        restore(),
+        blocklist(),
+        export(),
+        import_suggestions(),
+        config(),
+    ]
 }
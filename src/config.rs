@@ -1,4 +1,8 @@
-use std::{fs, time::Duration};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use chrono::{NaiveTime, Weekday};
 use color_eyre::{
@@ -6,42 +10,102 @@ use color_eyre::{
     eyre::{Context, eyre},
 };
 use figment::{
-    Figment,
-    providers::{Format, Toml},
+    Figment, Provider,
+    providers::{Env, Format, Serialized, Toml},
+    value::{Dict, Uncased, UncasedStr},
 };
 use poise::serenity_prelude::*;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::util::{deserialize_duration, serialize_duration};
+
+/// The prefix of environment variables that override config keys, e.g. `ARTISTIC_POLL_THRESHOLD`.
+const ENV_PREFIX: &str = "ARTISTIC_";
+
+/// The ID of the category built from the legacy internal channels.
+const LEGACY_INTERNAL_CATEGORY: &str = "internal";
+
+/// Returns the environment variables that override config keys.
+fn env() -> Env {
+    Env::prefixed(ENV_PREFIX).filter_map(env_key)
+}
+
+/// Maps the name of an environment variable without the prefix to the config key it overrides.
+fn env_key(name: &UncasedStr) -> Option<Uncased<'_>> {
+    // the keys of the config are kebab-case, and the data directory is not part of it
+    (name != "data_dir").then(|| name.as_str().replace('_', "-").into())
+}
+
+/// The directory that the config, the database and the other files of the bot are stored in.
+#[derive(Debug, Clone)]
+pub struct DataDir(PathBuf);
+
+impl DataDir {
+    pub fn new(path: impl Into<PathBuf>) -> DataDir {
+        DataDir(path.into())
+    }
 
-use crate::util::deserialize_duration;
+    /// Returns the path of the directory.
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Returns the path of the config file.
+    pub fn config(&self) -> PathBuf {
+        self.0.join("config.toml")
+    }
+
+    /// Returns the path of the database.
+    pub fn database(&self) -> PathBuf {
+        self.0.join("database.sqlite")
+    }
+
+    /// Returns the path of the legacy biweekly flag.
+    pub fn biweekly_flag(&self) -> PathBuf {
+        self.0.join("biweekly_flag.bin")
+    }
+}
 
 /// The configuration for the bot.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
     /// The operating guild ID.
     #[serde(rename = "guild-id")]
     pub guild: GuildId,
 
-    /// The internal artist channel ID.
+    /// The feature categories.
+    ///
+    /// If not set, the `external` and `internal` categories are built from the legacy channel IDs below.
+    #[serde(default)]
+    pub categories: Vec<Category>,
+
+    /// The legacy internal artist channel ID.
     #[serde(rename = "internal-channel-id")]
-    pub internal_channel: ChannelId,
+    #[serde(default, skip_serializing)]
+    internal_channel: Option<ChannelId>,
 
-    /// The internal artist poll channel ID.
+    /// The legacy internal artist poll channel ID.
     #[serde(rename = "internal-poll-channel-id")]
-    pub internal_poll_channel: ChannelId,
+    #[serde(default, skip_serializing)]
+    internal_poll_channel: Option<ChannelId>,
 
-    /// The external artist channel ID.
+    /// The legacy external artist channel ID.
     #[serde(rename = "external-channel-id")]
-    pub external_channel: ChannelId,
+    #[serde(default, skip_serializing)]
+    external_channel: Option<ChannelId>,
 
-    /// The external artist poll channel ID.
+    /// The legacy external artist poll channel ID.
     #[serde(rename = "external-poll-channel-id")]
-    pub external_poll_channel: ChannelId,
+    #[serde(default, skip_serializing)]
+    external_poll_channel: Option<ChannelId>,
 
     /// The form timeout duration in seconds.
     ///
     /// If the form is not submitted within this duration, it will be cancelled.
     #[serde(rename = "form-timeout")]
     #[serde(deserialize_with = "deserialize_duration")]
+    #[serde(serialize_with = "serialize_duration")]
     pub form_timeout: Duration,
 
     /// The weekday to post announcements on.
@@ -52,46 +116,620 @@ pub struct Config {
     #[serde(rename = "announcement-time")]
     pub announcement_time: NaiveTime,
 
-    /// The role ID to ping in announcements.
+    /// The role ID to ping in announcements of categories without their own `ping-role-id`.
     #[serde(rename = "announcement-role-id")]
-    pub announcement_role: RoleId,
+    #[serde(default)]
+    pub announcement_role: Option<RoleId>,
 
-    /// The minimum number of votes required to pass a poll.
+    /// The minimum number of votes required to pass a poll in categories without their own `poll-threshold`.
+    ///
+    /// Depending on `poll-rule`, this is the number of upvotes, the net score or the number of voters.
     #[serde(rename = "poll-threshold")]
     pub poll_threshold: usize,
 
+    /// The voting window of polls in seconds.
+    ///
+    /// Pending polls expire after this duration. If zero, polls never expire.
+    #[serde(rename = "poll-voting-window")]
+    #[serde(deserialize_with = "deserialize_duration")]
+    #[serde(serialize_with = "serialize_duration")]
+    #[serde(default)]
+    pub poll_voting_window: Duration,
+
+    /// The rule that decides when a poll passes.
+    #[serde(rename = "poll-rule")]
+    #[serde(default)]
+    pub poll_rule: PollRule,
+
+    /// The minimum percentage of upvotes among all votes for the `approval` poll rule.
+    #[serde(rename = "poll-approval-percent")]
+    #[serde(default = "default_approval_percent")]
+    pub poll_approval_percent: usize,
+
     /// The poll facilitator role ID.
     #[serde(rename = "facilitator-role-id")]
     pub facilitator_role: RoleId,
 
+    /// The channel ID that facilitators are notified in.
+    ///
+    /// If not set, facilitator notifications are only logged.
+    #[serde(rename = "facilitator-channel-id")]
+    #[serde(default)]
+    pub facilitator_channel: Option<ChannelId>,
+
+    /// What to do with announcement slots that were missed while the bot was offline.
+    #[serde(rename = "missed-announcement-policy")]
+    #[serde(default)]
+    pub missed_announcement_policy: MissedAnnouncementPolicy,
+
+    /// The message posted in the announcement channel when a slot can't be filled because the queue is empty.
+    ///
+    /// If not set, nothing is posted. Facilitators are notified either way.
+    #[serde(rename = "empty-queue-message")]
+    #[serde(default)]
+    pub empty_queue_message: Option<String>,
+
+    /// Whether the facilitator role is pinged when a slot can't be filled because the queue is empty.
+    ///
+    /// Without a facilitator channel, the notification is only logged and nobody is pinged.
+    #[serde(rename = "empty-queue-ping-facilitators")]
+    #[serde(default)]
+    pub empty_queue_ping_facilitators: bool,
+
+    /// Facilitators are warned once an announcement leaves fewer approved suggestions than this in its queue.
+    ///
+    /// If zero, there is no warning.
+    #[serde(rename = "low-queue-threshold")]
+    #[serde(default)]
+    pub low_queue_threshold: usize,
+
+    /// The maximum number of pending or approved suggestions per user in each category.
+    ///
+    /// If zero, there is no limit. Facilitators are exempt.
+    #[serde(rename = "suggestion-limit")]
+    #[serde(default)]
+    pub suggestion_limit: usize,
+
+    /// The minimum duration in seconds between two suggestions of a user.
+    ///
+    /// If zero, there is no cooldown. Facilitators are exempt.
+    #[serde(rename = "suggestion-cooldown")]
+    #[serde(deserialize_with = "deserialize_duration")]
+    #[serde(serialize_with = "serialize_duration")]
+    #[serde(default)]
+    pub suggestion_cooldown: Duration,
+
+    /// How to handle submissions that look like duplicates of existing or past suggestions.
+    #[serde(rename = "duplicate-policy")]
+    #[serde(default)]
+    pub duplicate_policy: DuplicatePolicy,
+
+    /// Hosts that may not be linked in suggestions, including their subdomains.
+    #[serde(rename = "link-host-denylist")]
+    #[serde(default)]
+    pub link_host_denylist: Vec<String>,
+
+    /// Whether editing a suggestion removes the votes of its pending poll.
+    #[serde(rename = "edit-resets-votes")]
+    #[serde(default)]
+    pub edit_resets_votes: bool,
+
+    /// Whether veto reasons are shown in vetoed polls.
+    ///
+    /// The reason is always sent to the author of the suggestion.
+    #[serde(rename = "show-veto-reasons")]
+    #[serde(default = "default_true")]
+    pub show_veto_reasons: bool,
+
+    /// Whether the config is reloaded when `config.toml` changes.
+    ///
+    /// It can always be reloaded with `/config reload`. This is read from the first guild and applies to every guild.
+    #[serde(rename = "reload-on-change")]
+    #[serde(default)]
+    pub reload_on_change: bool,
+
     /// The duration in seconds between announcements.
     /// If not zero, announcements will be sent every `announcement-period` seconds instead of the normal schedule.
     ///
     /// This is used for testing.
     #[serde(rename = "debug-announcement-period")]
     #[serde(deserialize_with = "deserialize_duration")]
+    #[serde(serialize_with = "serialize_duration")]
     #[serde(default)]
     pub debug_announcement_period: Duration,
 }
 
+/// The per-guild overrides of the config.
+#[derive(Deserialize)]
+struct Guilds {
+    #[serde(default)]
+    guilds: Vec<Dict>,
+}
+
+/// A feature category with its own polls, queue and announcements.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Category {
+    /// The ID stored in the database. It must not change once the category has suggestions.
+    pub id: String,
+
+    /// The name shown in commands and messages.
+    pub name: String,
+
+    /// The title of poll embeds.
+    #[serde(rename = "poll-title")]
+    pub poll_title: String,
+
+    /// The title of announcement embeds.
+    #[serde(rename = "announcement-title")]
+    pub announcement_title: String,
+
+    /// The poll channel ID.
+    #[serde(rename = "poll-channel-id")]
+    pub poll_channel: ChannelId,
+
+    /// The announcement channel ID.
+    #[serde(rename = "announcement-channel-id")]
+    pub announcement_channel: ChannelId,
+
+    /// The role ID to ping in announcements. Defaults to `announcement-role-id`.
+    #[serde(rename = "ping-role-id")]
+    #[serde(default)]
+    pub ping_role: Option<RoleId>,
+
+    /// The minimum number of votes required to pass a poll. Defaults to `poll-threshold`.
+    #[serde(rename = "poll-threshold")]
+    #[serde(default)]
+    pub poll_threshold: Option<usize>,
+
+    /// The category is announced every `cadence` announcement slots.
+    #[serde(default = "default_cadence")]
+    pub cadence: u32,
+}
+
+fn default_cadence() -> u32 {
+    1
+}
+
+/// The rule that decides when a poll passes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PollRule {
+    /// At least `poll-threshold` upvotes.
+    #[default]
+    Upvotes,
+    /// At least `poll-threshold` more upvotes than downvotes.
+    NetScore,
+    /// At least `poll-approval-percent` percent upvotes with at least `poll-threshold` voters.
+    Approval,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_approval_percent() -> usize {
+    60
+}
+
+/// What to do with missed announcement slots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MissedAnnouncementPolicy {
+    /// Post the announcements of the latest missed slot on startup and skip the earlier ones.
+    #[default]
+    PostLate,
+    /// Record the missed slots as skipped.
+    Skip,
+}
+
+/// How to handle submissions that look like duplicates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// Show the duplicates and let the submitter confirm the submission.
+    #[default]
+    Warn,
+    /// Show the duplicates and reject the submission.
+    Block,
+}
+
 impl Config {
-    pub fn load() -> Result<Config> {
-        if !fs::exists("./data/config.toml")
-            .wrap_err("failed to check if ./data/config.toml exists")?
+    /// Loads the config of every guild.
+    ///
+    /// The top-level keys apply to every guild, and each `[[guilds]]` table overrides them for one guild.
+    /// Without `[[guilds]]` tables, the top level is the config of the only guild.
+    /// Environment variables like `ARTISTIC_POLL_THRESHOLD` override a key in every guild.
+    pub fn load(data_dir: &DataDir) -> Result<Vec<Config>> {
+        let path = data_dir.config();
+
+        if !fs::exists(&path)
+            .wrap_err_with(|| format!("failed to check if {} exists", path.display()))?
         {
-            fs::write(
-                "./data/config.toml",
-                include_str!("../assets/default-config.toml"),
-            )
-            .wrap_err("failed to write ./data/config.toml")?;
+            fs::write(&path, include_str!("../assets/default-config.toml"))
+                .wrap_err_with(|| format!("failed to write {}", path.display()))?;
             return Err(eyre!(
-                "./data/config.toml not found, created default config"
+                "{} not found, created default config",
+                path.display()
             ));
         }
 
-        Figment::new()
-            .merge(Toml::file_exact("./data/config.toml"))
-            .extract::<Config>()
-            .wrap_err("failed to load config")
+        Config::extract(Figment::new().merge(Toml::file_exact(&path)), env())
+    }
+
+    /// Extracts the config of every guild from the config file, with `overrides` applied to every guild.
+    fn extract(figment: Figment, overrides: impl Provider + Clone) -> Result<Vec<Config>> {
+        let guilds = figment
+            .extract::<Guilds>()
+            .wrap_err("failed to load config")?
+            .guilds;
+
+        let mut configs = if guilds.is_empty() {
+            vec![
+                figment
+                    .merge(overrides)
+                    .extract::<Config>()
+                    .wrap_err("failed to load config")?,
+            ]
+        } else {
+            guilds
+                .into_iter()
+                .enumerate()
+                .map(|(index, guild)| {
+                    figment
+                        .clone()
+                        .merge(Serialized::defaults(guild))
+                        .merge(overrides.clone())
+                        .extract::<Config>()
+                        .wrap_err_with(|| {
+                            format!("failed to load the config of guild #{}", index + 1)
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        for config in &mut configs {
+            let guild = config.guild;
+            config
+                .resolve_categories()
+                .wrap_err_with(|| format!("invalid config for guild {guild}"))?;
+        }
+
+        for (index, config) in configs.iter().enumerate() {
+            if configs[..index]
+                .iter()
+                .any(|other| other.guild == config.guild)
+            {
+                return Err(eyre!("duplicate guild ID {}", config.guild));
+            }
+        }
+
+        Ok(configs)
+    }
+
+    /// Returns the category that took over the legacy internal announcements, if it is still configured.
+    pub fn legacy_internal_category(&self) -> Option<&Category> {
+        self.categories
+            .iter()
+            .find(|category| category.id == LEGACY_INTERNAL_CATEGORY)
+    }
+
+    /// Builds the legacy categories if none are configured and checks the categories.
+    fn resolve_categories(&mut self) -> Result<()> {
+        if self.categories.is_empty() {
+            let (
+                Some(external_channel),
+                Some(external_poll_channel),
+                Some(internal_channel),
+                Some(internal_poll_channel),
+            ) = (
+                self.external_channel,
+                self.external_poll_channel,
+                self.internal_channel,
+                self.internal_poll_channel,
+            )
+            else {
+                return Err(eyre!("no categories configured"));
+            };
+
+            self.categories = vec![
+                Category {
+                    id: "external".into(),
+                    name: "Weekly External".into(),
+                    poll_title: "Weekly External Feature Artist Submission".into(),
+                    announcement_title: "New Weekly External Feature Artist! 🌟 🎵".into(),
+                    poll_channel: external_poll_channel,
+                    announcement_channel: external_channel,
+                    ping_role: None,
+                    poll_threshold: None,
+                    cadence: 1,
+                },
+                Category {
+                    id: LEGACY_INTERNAL_CATEGORY.into(),
+                    name: "Biweekly Internal".into(),
+                    poll_title: "Biweekly Internal Feature Artist Submission".into(),
+                    announcement_title: "New Biweekly Internal Feature Artist! 🌟 🎵".into(),
+                    poll_channel: internal_poll_channel,
+                    announcement_channel: internal_channel,
+                    ping_role: None,
+                    poll_threshold: None,
+                    cadence: 2,
+                },
+            ];
+        }
+
+        for (index, category) in self.categories.iter().enumerate() {
+            if category.id.is_empty() {
+                return Err(eyre!("category IDs must not be empty"));
+            }
+
+            if self.categories[..index]
+                .iter()
+                .any(|other| other.id == category.id)
+            {
+                return Err(eyre!("duplicate category ID `{}`", category.id));
+            }
+
+            if category.cadence == 0 {
+                return Err(eyre!(
+                    "the cadence of category `{}` must not be zero",
+                    category.id
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the category with the given ID.
+    pub fn category(&self, id: &str) -> Result<&Category> {
+        self.categories
+            .iter()
+            .find(|category| category.id == id)
+            .ok_or_else(|| eyre!("unknown category `{id}`"))
+    }
+
+    /// Returns the poll threshold of a category.
+    pub fn poll_threshold(&self, category: &Category) -> usize {
+        category.poll_threshold.unwrap_or(self.poll_threshold)
+    }
+
+    /// Returns the role to ping in announcements of a category, if any.
+    pub fn ping_role(&self, category: &Category) -> Option<RoleId> {
+        category.ping_role.or(self.announcement_role)
+    }
+
+    /// Lists the problems that can be found without connecting to Discord.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.poll_threshold == 0 {
+            problems.push("`poll-threshold` must not be zero".into());
+        }
+
+        for category in &self.categories {
+            if category.poll_threshold == Some(0) {
+                problems.push(format!(
+                    "`poll-threshold` of category `{}` must not be zero",
+                    category.id
+                ));
+            }
+        }
+
+        if self.form_timeout.is_zero() {
+            problems.push("`form-timeout` must not be zero".into());
+        }
+
+        problems
+    }
+}
+
+/// Lists the keys whose values differ between two configs as `key: old -> new`.
+pub fn diff(old: &Config, new: &Config) -> Result<Vec<String>> {
+    let old = serde_json::to_value(old).wrap_err("failed to serialize config")?;
+    let new = serde_json::to_value(new).wrap_err("failed to serialize config")?;
+
+    let mut changes = Vec::new();
+    diff_values("", &old, &new, &mut changes);
+
+    Ok(changes)
+}
+
+/// Adds the differences between two values at `path` to `changes`, descending into tables and arrays.
+fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let keys = old
+                .keys()
+                .chain(new.keys().filter(|key| !old.contains_key(*key)));
+
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+
+                diff_values(
+                    &path,
+                    old.get(key).unwrap_or(&Value::Null),
+                    new.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for index in 0..old.len().max(new.len()) {
+                diff_values(
+                    &format!("{path}[{index}]"),
+                    old.get(index).unwrap_or(&Value::Null),
+                    new.get(index).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        (old, new) if old != new => changes.push(format!("{path}: {old} -> {new}")),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// The config of one guild with only the required keys and two categories.
+    const BASE: &str = r#"
+        guild-id = 1
+        form-timeout = 600
+        announcement-weekday = "monday"
+        announcement-time = "12:00"
+        poll-threshold = 3
+        facilitator-role-id = 2
+
+        [[categories]]
+        id = "external"
+        name = "Weekly External"
+        poll-title = "External Poll"
+        announcement-title = "External Announcement"
+        poll-channel-id = 3
+        announcement-channel-id = 4
+
+        [[categories]]
+        id = "internal"
+        name = "Biweekly Internal"
+        poll-title = "Internal Poll"
+        announcement-title = "Internal Announcement"
+        poll-channel-id = 5
+        announcement-channel-id = 6
+        cadence = 2
+    "#;
+
+    #[test]
+    fn test_env_key() {
+        const TESTS: &[(&str, Option<&str>)] = &[
+            ("POLL_THRESHOLD", Some("poll-threshold")),
+            (
+                "empty_queue_ping_facilitators",
+                Some("empty-queue-ping-facilitators"),
+            ),
+            ("DATA_DIR", None),
+        ];
+
+        for (name, expected) in TESTS {
+            assert_eq!(env_key(UncasedStr::new(name)), expected.map(Uncased::from));
+        }
+    }
+
+    #[test]
+    fn test_problems() {
+        const TESTS: &[(&str, &[&str])] = &[
+            ("", &[]),
+            (
+                "poll-threshold = 0\nform-timeout = 0",
+                &[
+                    "`poll-threshold` must not be zero",
+                    "`form-timeout` must not be zero",
+                ],
+            ),
+            (
+                r#"
+                [[categories]]
+                id = "external"
+                name = "Weekly External"
+                poll-title = "External Poll"
+                announcement-title = "External Announcement"
+                poll-channel-id = 3
+                announcement-channel-id = 4
+                poll-threshold = 0
+                "#,
+                &["`poll-threshold` of category `external` must not be zero"],
+            ),
+        ];
+
+        for (toml, expected) in TESTS {
+            let figment = Figment::from(Toml::string(BASE)).merge(Toml::string(toml));
+            let config = Config::extract(figment, Serialized::defaults(Dict::new()))
+                .unwrap()
+                .remove(0);
+
+            assert_eq!(config.problems(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_diff_values() {
+        let old = json!({
+            "poll-threshold": 3,
+            "form-timeout": 600,
+            "categories": [{ "id": "external", "cadence": 1 }],
+        });
+        let new = json!({
+            "poll-threshold": 5,
+            "form-timeout": 600,
+            "categories": [{ "id": "external", "cadence": 2 }, { "id": "internal" }],
+            "empty-queue-message": "None this week",
+        });
+
+        let mut changes = Vec::new();
+        diff_values("", &old, &new, &mut changes);
+
+        assert_eq!(
+            changes,
+            [
+                "categories[0].cadence: 1 -> 2",
+                "categories[1]: null -> {\"id\":\"internal\"}",
+                "poll-threshold: 3 -> 5",
+                "empty-queue-message: null -> \"None this week\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_guilds() {
+        let guilds = r#"
+            [[guilds]]
+            guild-id = 10
+
+            [[guilds]]
+            guild-id = 20
+            poll-threshold = 5
+            suggestion-limit = 4
+
+            [[guilds.categories]]
+            id = "only"
+            name = "Only"
+            poll-title = "Poll"
+            announcement-title = "Announcement"
+            poll-channel-id = 7
+            announcement-channel-id = 8
+        "#;
+
+        // the top level applies to every guild, and the overrides apply over the guild tables
+        const TESTS: &[(u64, usize, &[&str])] =
+            &[(10, 3, &["external", "internal"]), (20, 5, &["only"])];
+
+        let figment = Figment::from(Toml::string(BASE)).merge(Toml::string(guilds));
+        let configs = Config::extract(figment, Serialized::default("suggestion-limit", 2)).unwrap();
+
+        assert_eq!(configs.len(), TESTS.len());
+
+        for (config, (guild, poll_threshold, categories)) in configs.iter().zip(TESTS) {
+            assert_eq!(config.guild, GuildId::new(*guild));
+            assert_eq!(config.poll_threshold, *poll_threshold);
+            assert_eq!(config.suggestion_limit, 2);
+            assert_eq!(
+                config
+                    .categories
+                    .iter()
+                    .map(|category| category.id.as_str())
+                    .collect::<Vec<_>>(),
+                *categories
+            );
+        }
+
+        let duplicates = "[[guilds]]\nguild-id = 10\n\n[[guilds]]\nguild-id = 10";
+        let figment = Figment::from(Toml::string(BASE)).merge(Toml::string(duplicates));
+        assert!(Config::extract(figment, Serialized::defaults(Dict::new())).is_err());
     }
 }
@@ -1,38 +1,233 @@
+use std::path::Path;
+
+use chrono::NaiveDateTime;
 use color_eyre::{
     Result,
     eyre::{Context, eyre},
 };
-use futures::StreamExt;
 use itertools::Itertools;
-use poise::serenity_prelude::{MessageId, UserId};
-use sqlx::{Executor, SqlitePool, query, sqlite::SqliteConnectOptions};
+use poise::serenity_prelude::{GuildId, MessageId, UserId};
+use sqlx::{
+    Sqlite, SqlitePool, Transaction,
+    migrate::{Migrate, Migrator},
+    query,
+    sqlite::SqliteConnectOptions,
+};
+use tracing::info;
 
 use crate::{
-    types::{Poll, PollStatus, Suggestion},
-    util::artist,
+    config::DataDir,
+    duplicates::{Candidate, Origin, normalize_name},
+    export::{
+        Export, ExportFilter, ExportStatus, ExportedArchivedSuggestion, ExportedPoll,
+        ExportedSuggestion,
+    },
+    links::{Link, Platform, join_links, parse_stored_links},
+    schedule::SlotStatus,
+    types::{
+        ArchivedSuggestion, BlockedArtist, BlockedAttempt, BlockedUser, CategoryStats,
+        HistoryFilter, Poll, PollStatus, QueueEntry, RecordedBlockedAttempt, Stats, Suggestion,
+    },
 };
 
-/// Connects to the database, creating it if it doesn't exist.
-pub async fn connect() -> Result<SqlitePool> {
+/// Connects to the database in a data directory, creating it if it doesn't exist.
+pub async fn connect(data_dir: &DataDir) -> Result<SqlitePool> {
+    open(&data_dir.database()).await
+}
+
+/// Connects to the database at a path, creating it if it doesn't exist, and migrates it.
+pub async fn open(path: &Path) -> Result<SqlitePool> {
     let pool = SqlitePool::connect_with(
         SqliteConnectOptions::new()
-            .filename("./data/database.sqlite")
+            .filename(path)
             .create_if_missing(true),
     )
     .await
-    .wrap_err("failed to connect to ./data/database.sqlite")?;
+    .wrap_err_with(|| format!("failed to connect to {}", path.display()))?;
+
+    migrate(&pool).await?;
+
+    Ok(pool)
+}
 
-    create_tables(&pool).await?;
+/// Opens the database in a data directory read-only, without creating or migrating it.
+///
+/// Fails if the database is not migrated to the schema of this version of the bot.
+pub async fn open_read_only(data_dir: &DataDir) -> Result<SqlitePool> {
+    let path = data_dir.database();
+    let pool =
+        SqlitePool::connect_with(SqliteConnectOptions::new().filename(&path).read_only(true))
+            .await
+            .wrap_err_with(|| format!("failed to open {}", path.display()))?;
+
+    let latest = MIGRATOR.iter().map(|migration| migration.version).max();
+    let current = pool
+        .acquire()
+        .await
+        .wrap_err("failed to acquire connection")?
+        .list_applied_migrations()
+        .await
+        .wrap_err("failed to list applied migrations")?
+        .iter()
+        .map(|migration| migration.version)
+        .max();
+
+    if current != latest {
+        return Err(eyre!(
+            "the database schema version ({}) doesn't match the version of this binary ({}), start the bot once to migrate it",
+            current.unwrap_or_default(),
+            latest.unwrap_or_default()
+        ));
+    }
 
     Ok(pool)
 }
 
-/// Creates the tables if they don't exist.
-pub async fn create_tables(pool: &SqlitePool) -> Result<()> {
-    let mut stream = pool.execute_many(query(include_str!("../assets/create-tables.sql")));
+/// Writes a consistent copy of the database to a new file.
+pub async fn backup(pool: &SqlitePool, path: &Path) -> Result<()> {
+    let path = path.to_string_lossy();
+
+    query!("VACUUM INTO ?", path)
+        .execute(pool)
+        .await
+        .wrap_err_with(|| format!("failed to back up the database to {path}"))?;
+
+    Ok(())
+}
+
+/// The schema migrations embedded from `./migrations/`.
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Applies all pending migrations.
+///
+/// Refuses to run on a database that was migrated by a newer version of the bot.
+pub async fn migrate(pool: &SqlitePool) -> Result<()> {
+    let latest = MIGRATOR.iter().map(|migration| migration.version).max();
+
+    let mut connection = pool
+        .acquire()
+        .await
+        .wrap_err("failed to acquire connection")?;
+    connection
+        .ensure_migrations_table()
+        .await
+        .wrap_err("failed to create migrations table")?;
+    let applied = connection
+        .list_applied_migrations()
+        .await
+        .wrap_err("failed to list applied migrations")?;
+    drop(connection);
+
+    let current = applied.iter().map(|migration| migration.version).max();
+
+    if current > latest {
+        return Err(eyre!(
+            "the database schema version ({}) is newer than the latest version this binary supports ({}), refusing to start",
+            current.unwrap_or_default(),
+            latest.unwrap_or_default()
+        ));
+    }
+
+    let pending = MIGRATOR
+        .iter()
+        .filter(|migration| {
+            !applied
+                .iter()
+                .any(|applied| applied.version == migration.version)
+        })
+        .count();
+
+    if pending > 0 {
+        info!("Applying {pending} database migration(s)...");
+    }
+
+    MIGRATOR
+        .run(pool)
+        .await
+        .wrap_err("failed to apply migrations")?;
+
+    Ok(())
+}
+
+/// Assigns the rows stored before multi-guild support, which have the guild ID 0, to a guild.
+pub async fn adopt_legacy_rows(pool: &SqlitePool, guild: GuildId) -> Result<()> {
+    let guild_id = guild.get() as i64;
+
+    let mut transaction = pool.begin().await.wrap_err("failed to begin transaction")?;
+    let mut adopted = 0;
+
+    // the table names can't be bound, so every table has its own query
+    for result in [
+        query!(
+            "UPDATE suggestions SET guild_id = ? WHERE guild_id = 0",
+            guild_id
+        )
+        .execute(&mut *transaction)
+        .await,
+        query!("UPDATE polls SET guild_id = ? WHERE guild_id = 0", guild_id)
+            .execute(&mut *transaction)
+            .await,
+        query!(
+            "UPDATE deleted_suggestions SET guild_id = ? WHERE guild_id = 0",
+            guild_id
+        )
+        .execute(&mut *transaction)
+        .await,
+        query!(
+            "UPDATE notices SET guild_id = ? WHERE guild_id = 0",
+            guild_id
+        )
+        .execute(&mut *transaction)
+        .await,
+        query!(
+            "UPDATE blocked_users SET guild_id = ? WHERE guild_id = 0",
+            guild_id
+        )
+        .execute(&mut *transaction)
+        .await,
+        query!(
+            "UPDATE blocked_artists SET guild_id = ? WHERE guild_id = 0",
+            guild_id
+        )
+        .execute(&mut *transaction)
+        .await,
+        query!(
+            "UPDATE blocked_attempts SET guild_id = ? WHERE guild_id = 0",
+            guild_id
+        )
+        .execute(&mut *transaction)
+        .await,
+        query!(
+            "UPDATE queue_skips SET guild_id = ? WHERE guild_id = 0",
+            guild_id
+        )
+        .execute(&mut *transaction)
+        .await,
+        query!(
+            "UPDATE last_announcements SET guild_id = ? WHERE guild_id = 0",
+            guild_id
+        )
+        .execute(&mut *transaction)
+        .await,
+        query!(
+            "UPDATE announcement_slots SET guild_id = ? WHERE guild_id = 0",
+            guild_id
+        )
+        .execute(&mut *transaction)
+        .await,
+    ] {
+        adopted += result
+            .wrap_err("failed to adopt legacy rows")?
+            .rows_affected();
+    }
+
+    transaction
+        .commit()
+        .await
+        .wrap_err("failed to commit transaction")?;
 
-    while let Some(result) = stream.next().await {
-        result.wrap_err("failed to create table")?;
+    if adopted > 0 {
+        info!("Assigned {adopted} row(s) from before multi-guild support to guild {guild}");
     }
 
     Ok(())
@@ -41,37 +236,186 @@ pub async fn create_tables(pool: &SqlitePool) -> Result<()> {
 /// Inserts a new suggestion into the database.
 pub async fn insert_suggestion(
     pool: &SqlitePool,
+    guild: GuildId,
     suggestion: &Suggestion,
     poll_id: u64,
 ) -> Result<()> {
+    let guild_id = guild.get() as i64;
     let user_id = suggestion.user_id.get() as i64;
+    let links = join_links(&suggestion.links);
     let poll_id = poll_id as i64;
 
-    query!(
-        "INSERT INTO suggestions (user_id, username, artist_name, album_name, links, notes, internal, poll_id)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    let mut transaction = pool.begin().await.wrap_err("failed to begin transaction")?;
+
+    let suggestion_id = query!(
+        "INSERT INTO suggestions (guild_id, user_id, username, artist_name, album_name, links, notes, category, poll_id)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        guild_id,
         user_id,
         suggestion.username,
         suggestion.artist_name,
         suggestion.album_name,
-        suggestion.links,
+        links,
         suggestion.notes,
-        suggestion.internal,
+        suggestion.category,
         poll_id
     )
-    .execute(pool)
+    .execute(&mut *transaction)
+    .await
+    .wrap_err("failed to insert suggestion")?
+    .last_insert_rowid();
+
+    insert_links(&mut transaction, suggestion_id, &suggestion.links).await?;
+
+    transaction
+        .commit()
+        .await
+        .wrap_err("failed to commit transaction")?;
+
+    Ok(())
+}
+
+/// Inserts an approved suggestion without a poll at the back of its queue and returns its ID.
+pub async fn insert_queued_suggestion(
+    pool: &SqlitePool,
+    guild: GuildId,
+    suggestion: &Suggestion,
+) -> Result<u64> {
+    let guild_id = guild.get() as i64;
+    let user_id = suggestion.user_id.get() as i64;
+    let links = join_links(&suggestion.links);
+
+    let mut transaction = pool.begin().await.wrap_err("failed to begin transaction")?;
+
+    let suggestion_id = query!(
+        "INSERT INTO suggestions (guild_id, user_id, username, artist_name, album_name, links, notes, category, approved, approved_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, TRUE, CURRENT_TIMESTAMP)",
+        guild_id,
+        user_id,
+        suggestion.username,
+        suggestion.artist_name,
+        suggestion.album_name,
+        links,
+        suggestion.notes,
+        suggestion.category
+    )
+    .execute(&mut *transaction)
+    .await
+    .wrap_err("failed to insert suggestion")?
+    .last_insert_rowid();
+
+    insert_links(&mut transaction, suggestion_id, &suggestion.links).await?;
+
+    transaction
+        .commit()
+        .await
+        .wrap_err("failed to commit transaction")?;
+
+    Ok(suggestion_id as u64)
+}
+
+/// Updates the fields of the suggestion with the given ID, replacing its links.
+pub async fn update_suggestion(
+    pool: &SqlitePool,
+    suggestion_id: u64,
+    suggestion: &Suggestion,
+) -> Result<()> {
+    let suggestion_id = suggestion_id as i64;
+    let links = join_links(&suggestion.links);
+
+    let mut transaction = pool.begin().await.wrap_err("failed to begin transaction")?;
+
+    query!(
+        "UPDATE suggestions
+         SET artist_name = ?, album_name = ?, links = ?, notes = ?
+         WHERE id = ?",
+        suggestion.artist_name,
+        suggestion.album_name,
+        links,
+        suggestion.notes,
+        suggestion_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .wrap_err("failed to update suggestion")?;
+
+    insert_links(&mut transaction, suggestion_id, &suggestion.links).await?;
+
+    transaction
+        .commit()
+        .await
+        .wrap_err("failed to commit transaction")?;
+
+    Ok(())
+}
+
+/// Replaces the links of the suggestion with the given ID.
+async fn insert_links(
+    transaction: &mut Transaction<'_, Sqlite>,
+    suggestion_id: i64,
+    links: &[Link],
+) -> Result<()> {
+    query!(
+        "DELETE FROM suggestion_links
+         WHERE suggestion_id = ?",
+        suggestion_id
+    )
+    .execute(&mut **transaction)
     .await
-    .wrap_err("failed to insert suggestion")?;
+    .wrap_err("failed to remove suggestion links")?;
+
+    for link in links {
+        let platform = link.platform.id();
+
+        query!(
+            "INSERT INTO suggestion_links (suggestion_id, url, platform)
+             VALUES (?, ?, ?)",
+            suggestion_id,
+            link.url,
+            platform
+        )
+        .execute(&mut **transaction)
+        .await
+        .wrap_err("failed to insert suggestion link")?;
+    }
 
     Ok(())
 }
 
+/// Fetches the links of the suggestion with the given ID.
+///
+/// Suggestions stored before links had their own table fall back to the `links` column.
+async fn fetch_links(pool: &SqlitePool, suggestion_id: i64, links: &str) -> Result<Vec<Link>> {
+    let rows = query!(
+        "SELECT url, platform
+         FROM suggestion_links
+         WHERE suggestion_id = ?
+         ORDER BY id",
+        suggestion_id
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to fetch suggestion links")?;
+
+    if rows.is_empty() {
+        return Ok(parse_stored_links(links));
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Link {
+            url: row.url,
+            platform: Platform::from_id(&row.platform),
+        })
+        .collect())
+}
+
 /// Fetches a suggestion by its poll ID.
 pub async fn fetch_suggestion(pool: &SqlitePool, poll_id: u64) -> Result<Suggestion> {
     let poll_id = poll_id as i64;
 
     let suggestion = query!(
-        "SELECT id, user_id, username, artist_name, album_name, links, notes, internal
+        "SELECT id, user_id, username, artist_name, album_name, links, notes, category
          FROM suggestions
          WHERE poll_id = ?",
         poll_id
@@ -87,9 +431,9 @@ pub async fn fetch_suggestion(pool: &SqlitePool, poll_id: u64) -> Result<Suggest
         username: suggestion.username,
         artist_name: suggestion.artist_name,
         album_name: suggestion.album_name,
-        links: suggestion.links,
+        links: fetch_links(pool, suggestion.id, &suggestion.links).await?,
         notes: suggestion.notes,
-        internal: suggestion.internal,
+        category: suggestion.category,
     })
 }
 
@@ -99,7 +443,7 @@ pub async fn approve_suggestion(pool: &SqlitePool, poll_id: u64) -> Result<()> {
 
     query!(
         "UPDATE suggestions
-         SET approved = TRUE
+         SET approved = TRUE, approved_at = CURRENT_TIMESTAMP
          WHERE poll_id = ?",
         poll_id
     )
@@ -110,94 +454,974 @@ pub async fn approve_suggestion(pool: &SqlitePool, poll_id: u64) -> Result<()> {
     Ok(())
 }
 
-/// Fetches the oldest approved suggestion.
-pub async fn pick_suggestion(pool: &SqlitePool, internal: bool) -> Result<Suggestion> {
+/// Fetches the approved suggestions in the order they will be picked by [`pick_suggestion`].
+pub async fn fetch_queue(
+    pool: &SqlitePool,
+    guild: GuildId,
+    category: &str,
+) -> Result<Vec<QueueEntry>> {
+    let guild_id = guild.get() as i64;
+
+    // the order must match the one in `pick_suggestion`
+    Ok(query!(
+        "SELECT id, user_id, username, artist_name, album_name, approved_at
+         FROM suggestions
+         WHERE guild_id = ? AND category = ? AND approved = TRUE
+         ORDER BY queue_position IS NULL, queue_position, timestamp",
+        guild_id,
+        category
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to fetch queue")?
+    .into_iter()
+    .map(|row| QueueEntry {
+        id: row.id as u64,
+        user_id: UserId::new(row.user_id as u64),
+        username: row.username,
+        artist_name: row.artist_name,
+        album_name: row.album_name,
+        approved_at: row.approved_at,
+    })
+    .collect())
+}
+
+/// Moves an approved suggestion to a position in its queue, or to the back if `position` is `None`.
+///
+/// Returns the new position, or `None` if the suggestion isn't queued.
+pub async fn move_in_queue(
+    pool: &SqlitePool,
+    guild: GuildId,
+    suggestion_id: u64,
+    position: Option<usize>,
+) -> Result<Option<usize>> {
+    let guild_id = guild.get() as i64;
+    let suggestion_id = suggestion_id as i64;
+
+    // the queue is read and rewritten in one transaction, so that concurrent changes aren't lost
+    let mut transaction = pool.begin().await.wrap_err("failed to begin transaction")?;
+
+    let Some(category) = query!(
+        "SELECT category
+         FROM suggestions
+         WHERE id = ? AND guild_id = ? AND approved = TRUE",
+        suggestion_id,
+        guild_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .wrap_err("failed to fetch suggestion")?
+    .map(|row| row.category) else {
+        return Ok(None);
+    };
+
+    // the order must match the one in `pick_suggestion`
+    let mut suggestion_ids = query!(
+        "SELECT id
+         FROM suggestions
+         WHERE guild_id = ? AND category = ? AND approved = TRUE
+         ORDER BY queue_position IS NULL, queue_position, timestamp",
+        guild_id,
+        category
+    )
+    .fetch_all(&mut *transaction)
+    .await
+    .wrap_err("failed to fetch queue")?
+    .into_iter()
+    .map(|row| row.id)
+    .collect::<Vec<_>>();
+
+    let Some(position) = move_entry(&mut suggestion_ids, &suggestion_id, position) else {
+        return Ok(None);
+    };
+
+    // every entry gets an explicit position so that the order is kept as is
+    for (queue_position, suggestion_id) in suggestion_ids.iter().enumerate() {
+        let queue_position = queue_position as i64;
+
+        query!(
+            "UPDATE suggestions
+             SET queue_position = ?
+             WHERE id = ?",
+            queue_position,
+            suggestion_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .wrap_err("failed to update queue position")?;
+    }
+
+    transaction
+        .commit()
+        .await
+        .wrap_err("failed to commit transaction")?;
+
+    Ok(Some(position))
+}
+
+/// Moves an entry of a queue to a position, or to the back if `position` is `None`.
+///
+/// Returns the new position, or `None` if the entry isn't in the queue.
+fn move_entry<T: PartialEq>(
+    queue: &mut Vec<T>,
+    entry: &T,
+    position: Option<usize>,
+) -> Option<usize> {
+    let index = queue.iter().position(|other| other == entry)?;
+
+    let entry = queue.remove(index);
+    let position = position.unwrap_or(queue.len()).min(queue.len());
+    queue.insert(position, entry);
+
+    Some(position)
+}
+
+/// Sets whether the next pick of a category is skipped.
+pub async fn set_queue_skip(
+    pool: &SqlitePool,
+    guild: GuildId,
+    category: &str,
+    skip: bool,
+) -> Result<()> {
+    let guild_id = guild.get() as i64;
+
+    if skip {
+        query!(
+            "INSERT OR IGNORE INTO queue_skips (guild_id, category)
+             VALUES (?, ?)",
+            guild_id,
+            category
+        )
+        .execute(pool)
+        .await
+        .wrap_err("failed to insert queue skip")?;
+    } else {
+        query!(
+            "DELETE FROM queue_skips
+             WHERE guild_id = ? AND category = ?",
+            guild_id,
+            category
+        )
+        .execute(pool)
+        .await
+        .wrap_err("failed to remove queue skip")?;
+    }
+
+    Ok(())
+}
+
+/// Returns whether the next pick of a category is skipped.
+pub async fn fetch_queue_skip(pool: &SqlitePool, guild: GuildId, category: &str) -> Result<bool> {
+    let guild_id = guild.get() as i64;
+
+    Ok(query!(
+        "SELECT category
+         FROM queue_skips
+         WHERE guild_id = ? AND category = ?",
+        guild_id,
+        category
+    )
+    .fetch_optional(pool)
+    .await
+    .wrap_err("failed to fetch queue skip")?
+    .is_some())
+}
+
+/// Removes the skip of the next pick of a category and returns whether there was one.
+pub async fn take_queue_skip(pool: &SqlitePool, guild: GuildId, category: &str) -> Result<bool> {
+    let guild_id = guild.get() as i64;
+
+    Ok(query!(
+        "DELETE FROM queue_skips
+         WHERE guild_id = ? AND category = ?",
+        guild_id,
+        category
+    )
+    .execute(pool)
+    .await
+    .wrap_err("failed to remove queue skip")?
+    .rows_affected()
+        > 0)
+}
+
+/// Fetches the next approved suggestion in the queue, if there is one.
+pub async fn pick_suggestion(
+    pool: &SqlitePool,
+    guild: GuildId,
+    category: &str,
+) -> Result<Option<Suggestion>> {
+    let guild_id = guild.get() as i64;
+
+    // the order must match the one in `fetch_queue`
     let suggestion = query!(
-        "SELECT id, user_id, username, artist_name, album_name, links, notes, internal
+        "SELECT id, user_id, username, artist_name, album_name, links, notes, category
          FROM suggestions
-         WHERE internal = ? AND approved = TRUE
-         ORDER BY timestamp
+         WHERE guild_id = ? AND category = ? AND approved = TRUE
+         ORDER BY queue_position IS NULL, queue_position, timestamp
          LIMIT 1",
-        internal
+        guild_id,
+        category
     )
     .fetch_optional(pool)
     .await
-    .wrap_err("failed to fetch suggestion")?
-    .ok_or(eyre!("no approved {} suggestion found", artist(internal)))?;
+    .wrap_err("failed to fetch suggestion")?;
 
-    Ok(Suggestion {
+    let Some(suggestion) = suggestion else {
+        return Ok(None);
+    };
+
+    Ok(Some(Suggestion {
         id: suggestion.id as u64,
         user_id: UserId::new(suggestion.user_id as u64),
         username: suggestion.username,
         artist_name: suggestion.artist_name,
         album_name: suggestion.album_name,
-        links: suggestion.links,
+        links: fetch_links(pool, suggestion.id, &suggestion.links).await?,
         notes: suggestion.notes,
-        internal: suggestion.internal,
-    })
+        category: suggestion.category,
+    }))
 }
 
 /// Removes the suggestion with the given ID and the associated poll.
-pub async fn remove_suggestion_and_poll(pool: &SqlitePool, suggestion_id: u64) -> Result<()> {
-    let suggestion_id = suggestion_id as i64;
+///
+/// The suggestion is archived with the status of its poll and the given veto `reason`.
+pub async fn remove_suggestion_and_poll(
+    pool: &SqlitePool,
+    suggestion_id: u64,
+    reason: Option<&str>,
+) -> Result<u64> {
+    let mut transaction = pool.begin().await.wrap_err("failed to begin transaction")?;
+    let archived_id = archive_suggestion(&mut transaction, suggestion_id as i64, reason).await?;
+
+    transaction
+        .commit()
+        .await
+        .wrap_err("failed to commit transaction")?;
+
+    Ok(archived_id)
+}
+
+/// Marks the poll with the given ID as expired and archives its suggestion in one transaction.
+pub async fn expire_poll(pool: &SqlitePool, poll_id: u64) -> Result<()> {
+    let poll_id = poll_id as i64;
+    let (status, votes, downvotes) = PollStatus::Expired.encode();
+    let status = status as i64;
+
+    let mut transaction = pool.begin().await.wrap_err("failed to begin transaction")?;
+
+    query!(
+        "UPDATE polls
+         SET status = ?, votes = ?, downvotes = ?
+         WHERE id = ?",
+        status,
+        votes,
+        downvotes,
+        poll_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .wrap_err("failed to update poll status")?;
+
+    let suggestion_id = query!(
+        "SELECT id
+         FROM suggestions
+         WHERE poll_id = ?",
+        poll_id
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .wrap_err("failed to fetch suggestion")?
+    .id;
+
+    archive_suggestion(&mut transaction, suggestion_id, None).await?;
 
+    transaction
+        .commit()
+        .await
+        .wrap_err("failed to commit transaction")
+}
+
+/// Moves a suggestion and its poll into `deleted_suggestions` and returns the archived suggestion's ID.
+async fn archive_suggestion(
+    transaction: &mut Transaction<'_, Sqlite>,
+    suggestion_id: i64,
+    reason: Option<&str>,
+) -> Result<u64> {
     let suggestion = query!(
         "DELETE FROM suggestions
          WHERE id = ?
          RETURNING *",
         suggestion_id
     )
-    .fetch_one(pool)
+    .fetch_one(&mut **transaction)
     .await
     .wrap_err("failed to remove suggestion")?;
 
-    let poll = query!(
-        "DELETE FROM polls
-         WHERE id = ?
-         RETURNING *",
-        suggestion.poll_id
+    query!(
+        "DELETE FROM suggestion_links
+         WHERE suggestion_id = ?",
+        suggestion_id
     )
-    .fetch_one(pool)
+    .execute(&mut **transaction)
     .await
-    .wrap_err("failed to remove poll")?;
+    .wrap_err("failed to remove suggestion links")?;
 
-    query!(
-        "INSERT INTO deleted_suggestions (user_id, username, artist_name, album_name, links, notes, internal, status, timestamp)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    // suggestions restored straight into the queue have no poll and are archived as completed
+    let status = match suggestion.poll_id {
+        Some(poll_id) => {
+            query!(
+                "DELETE FROM polls
+                 WHERE id = ?
+                 RETURNING status",
+                poll_id
+            )
+            .fetch_one(&mut **transaction)
+            .await
+            .wrap_err("failed to remove poll")?
+            .status
+        }
+        None => PollStatus::Completed.encode().0 as i64,
+    };
+
+    let archived_id = query!(
+        "INSERT INTO deleted_suggestions (guild_id, user_id, username, artist_name, album_name, links, notes, category, status, timestamp, reason, archived_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
+        suggestion.guild_id,
         suggestion.user_id,
         suggestion.username,
         suggestion.artist_name,
         suggestion.album_name,
         suggestion.links,
         suggestion.notes,
-        suggestion.internal,
-        poll.status,
-        suggestion.timestamp
+        suggestion.category,
+        status,
+        suggestion.timestamp,
+        reason
+    )
+    .execute(&mut **transaction)
+    .await
+    .wrap_err("failed to insert deleted suggestion")?
+    .last_insert_rowid();
+
+    Ok(archived_id as u64)
+}
+
+/// Fetches the slot of the last announcement of a category.
+pub async fn fetch_last_announcement(
+    pool: &SqlitePool,
+    guild: GuildId,
+    category: &str,
+) -> Result<Option<NaiveDateTime>> {
+    let guild_id = guild.get() as i64;
+
+    Ok(query!(
+        "SELECT timestamp
+         FROM last_announcements
+         WHERE guild_id = ? AND category = ?",
+        guild_id,
+        category
+    )
+    .fetch_optional(pool)
+    .await
+    .wrap_err("failed to fetch last announcement")?
+    .map(|row| row.timestamp))
+}
+
+/// Sets the slot of the last announcement of a category.
+pub async fn set_last_announcement(
+    pool: &SqlitePool,
+    guild: GuildId,
+    category: &str,
+    slot: NaiveDateTime,
+) -> Result<()> {
+    let guild_id = guild.get() as i64;
+
+    query!(
+        "INSERT INTO last_announcements (guild_id, category, timestamp)
+         VALUES (?, ?, ?)
+         ON CONFLICT (guild_id, category) DO UPDATE SET timestamp = excluded.timestamp",
+        guild_id,
+        category,
+        slot
     )
     .execute(pool)
     .await
-    .wrap_err("failed to insert deleted suggestion")?;
+    .wrap_err("failed to set last announcement")?;
 
     Ok(())
 }
 
+/// Fetches the latest handled announcement slot.
+///
+/// Falls back to the last announcement of any category, since slots weren't recorded before.
+pub async fn fetch_last_slot(pool: &SqlitePool, guild: GuildId) -> Result<Option<NaiveDateTime>> {
+    let guild_id = guild.get() as i64;
+
+    Ok(query!(
+        r#"SELECT slot AS "slot!: NaiveDateTime"
+           FROM (
+               SELECT slot FROM announcement_slots WHERE guild_id = ?1
+               UNION ALL
+               SELECT timestamp FROM last_announcements WHERE guild_id = ?1
+           )
+           ORDER BY slot DESC
+           LIMIT 1"#,
+        guild_id
+    )
+    .fetch_optional(pool)
+    .await
+    .wrap_err("failed to fetch last slot")?
+    .map(|row| row.slot))
+}
+
+/// Records how an announcement slot was handled.
+pub async fn insert_slot(
+    pool: &SqlitePool,
+    guild: GuildId,
+    slot: NaiveDateTime,
+    status: SlotStatus,
+) -> Result<()> {
+    let guild_id = guild.get() as i64;
+    let status = status.code() as i64;
+
+    query!(
+        "INSERT OR REPLACE INTO announcement_slots (guild_id, slot, status)
+         VALUES (?, ?, ?)",
+        guild_id,
+        slot,
+        status
+    )
+    .execute(pool)
+    .await
+    .wrap_err("failed to insert slot")?;
+
+    Ok(())
+}
+
+/// Counts the pending and approved suggestions of a user in a category.
+pub async fn count_suggestions(
+    pool: &SqlitePool,
+    guild: GuildId,
+    user_id: UserId,
+    category: &str,
+) -> Result<usize> {
+    let guild_id = guild.get() as i64;
+    let user_id = user_id.get() as i64;
+
+    let count = query!(
+        "SELECT COUNT(*) AS count
+         FROM suggestions
+         WHERE guild_id = ? AND user_id = ? AND category = ?",
+        guild_id,
+        user_id,
+        category
+    )
+    .fetch_one(pool)
+    .await
+    .wrap_err("failed to count suggestions")?
+    .count;
+
+    Ok(count as usize)
+}
+
+/// Fetches the submission time of the latest suggestion of a user, including archived ones.
+pub async fn fetch_last_submission(
+    pool: &SqlitePool,
+    guild: GuildId,
+    user_id: UserId,
+) -> Result<Option<NaiveDateTime>> {
+    let guild_id = guild.get() as i64;
+    let user_id = user_id.get() as i64;
+
+    Ok(query!(
+        r#"SELECT timestamp AS "timestamp: NaiveDateTime"
+           FROM (
+               SELECT timestamp FROM suggestions WHERE guild_id = ?1 AND user_id = ?2
+               UNION ALL
+               SELECT timestamp FROM deleted_suggestions WHERE guild_id = ?1 AND user_id = ?2
+           )
+           ORDER BY timestamp DESC
+           LIMIT 1"#,
+        guild_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .wrap_err("failed to fetch last submission")?
+    .map(|row| row.timestamp))
+}
+
+/// Fetches an archived suggestion by its ID.
+pub async fn fetch_archived_suggestion(
+    pool: &SqlitePool,
+    guild: GuildId,
+    archived_id: u64,
+) -> Result<Option<ArchivedSuggestion>> {
+    let guild_id = guild.get() as i64;
+    let archived_id = archived_id as i64;
+
+    let Some(row) = query!(
+        "SELECT id, user_id, username, artist_name, album_name, links, notes, category, status, timestamp, reason, archived_at
+         FROM deleted_suggestions
+         WHERE guild_id = ? AND id = ?",
+        guild_id,
+        archived_id
+    )
+    .fetch_optional(pool)
+    .await
+    .wrap_err("failed to fetch archived suggestion")?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(ArchivedSuggestion {
+        suggestion: Suggestion {
+            id: row.id as u64,
+            user_id: UserId::new(row.user_id as u64),
+            username: row.username,
+            artist_name: row.artist_name,
+            album_name: row.album_name,
+            links: parse_stored_links(&row.links),
+            notes: row.notes,
+            category: row.category,
+        },
+        status: PollStatus::parse(row.status as u64, None, None)?,
+        timestamp: row.timestamp,
+        archived_at: row.archived_at,
+        reason: row.reason,
+    }))
+}
+
+/// Searches the archived suggestions, most recently submitted first.
+pub async fn search_history(
+    pool: &SqlitePool,
+    guild: GuildId,
+    filter: &HistoryFilter,
+) -> Result<Vec<ArchivedSuggestion>> {
+    let guild_id = guild.get() as i64;
+    let search = filter.text.as_deref().and_then(search_query);
+    let status = filter
+        .status
+        .as_ref()
+        .map(|status| status.encode().0 as i64);
+    let user_id = filter.user_id.map(|user_id| user_id.get() as i64);
+
+    query!(
+        "SELECT id, user_id, username, artist_name, album_name, links, notes, category, status, timestamp, reason, archived_at
+         FROM deleted_suggestions
+         WHERE guild_id = ?5
+           AND (?1 IS NULL OR id IN (
+                   SELECT rowid
+                   FROM deleted_suggestions_search
+                   WHERE deleted_suggestions_search MATCH ?1
+               ))
+           AND (?2 IS NULL OR category = ?2)
+           AND (?3 IS NULL OR status = ?3)
+           AND (?4 IS NULL OR user_id = ?4)
+         ORDER BY timestamp DESC, id DESC",
+        search,
+        filter.category,
+        status,
+        user_id,
+        guild_id
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to search history")?
+    .into_iter()
+    .map(|row| {
+        Ok(ArchivedSuggestion {
+            suggestion: Suggestion {
+                id: row.id as u64,
+                user_id: UserId::new(row.user_id as u64),
+                username: row.username,
+                artist_name: row.artist_name,
+                album_name: row.album_name,
+                links: parse_stored_links(&row.links),
+                notes: row.notes,
+                category: row.category,
+            },
+            status: PollStatus::parse(row.status as u64, None, None)?,
+            timestamp: row.timestamp,
+            archived_at: row.archived_at,
+            reason: row.reason,
+        })
+    })
+    .collect()
+}
+
+/// Builds an FTS5 query that matches rows containing every word of `text` as a prefix, in any order.
+///
+/// Returns `None` if `text` has no words.
+fn search_query(text: &str) -> Option<String> {
+    let terms = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\"*"))
+        .collect::<Vec<_>>();
+
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Moves an archived suggestion back into `suggestions` and returns its new ID.
+///
+/// The original submission time and submitter are kept. `poll_id` is the ID of its new poll, if any.
+pub async fn restore_suggestion(
+    pool: &SqlitePool,
+    guild: GuildId,
+    archived_id: u64,
+    poll_id: Option<u64>,
+    approved: bool,
+) -> Result<u64> {
+    let guild_id = guild.get() as i64;
+    let archived_id = archived_id as i64;
+    let poll_id = poll_id.map(|poll_id| poll_id as i64);
+
+    let mut transaction = pool.begin().await.wrap_err("failed to begin transaction")?;
+
+    let archived = query!(
+        "DELETE FROM deleted_suggestions
+         WHERE guild_id = ? AND id = ?
+         RETURNING *",
+        guild_id,
+        archived_id
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .wrap_err("failed to remove archived suggestion")?;
+
+    let suggestion_id = query!(
+        "INSERT INTO suggestions (guild_id, user_id, username, artist_name, album_name, links, notes, category, poll_id, approved, approved_at, timestamp)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CASE WHEN ? THEN CURRENT_TIMESTAMP END, ?)",
+        archived.guild_id,
+        archived.user_id,
+        archived.username,
+        archived.artist_name,
+        archived.album_name,
+        archived.links,
+        archived.notes,
+        archived.category,
+        poll_id,
+        approved,
+        approved,
+        archived.timestamp
+    )
+    .execute(&mut *transaction)
+    .await
+    .wrap_err("failed to insert suggestion")?
+    .last_insert_rowid();
+
+    let links = parse_stored_links(&archived.links);
+    insert_links(&mut transaction, suggestion_id, &links).await?;
+
+    transaction
+        .commit()
+        .await
+        .wrap_err("failed to commit transaction")?;
+
+    Ok(suggestion_id as u64)
+}
+
+/// Blocks a user from submitting suggestions. Returns `false` if they were already blocked.
+pub async fn block_user(
+    pool: &SqlitePool,
+    guild: GuildId,
+    user_id: UserId,
+    reason: Option<&str>,
+) -> Result<bool> {
+    let guild_id = guild.get() as i64;
+    let user_id = user_id.get() as i64;
+
+    let result = query!(
+        "INSERT OR IGNORE INTO blocked_users (guild_id, user_id, reason)
+         VALUES (?, ?, ?)",
+        guild_id,
+        user_id,
+        reason
+    )
+    .execute(pool)
+    .await
+    .wrap_err("failed to block user")?;
+
+    Ok(result.rows_affected() != 0)
+}
+
+/// Unblocks a user. Returns `false` if they weren't blocked.
+pub async fn unblock_user(pool: &SqlitePool, guild: GuildId, user_id: UserId) -> Result<bool> {
+    let guild_id = guild.get() as i64;
+    let user_id = user_id.get() as i64;
+
+    let result = query!(
+        "DELETE FROM blocked_users
+         WHERE guild_id = ? AND user_id = ?",
+        guild_id,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .wrap_err("failed to unblock user")?;
+
+    Ok(result.rows_affected() != 0)
+}
+
+/// Checks whether a user is blocked.
+pub async fn is_user_blocked(pool: &SqlitePool, guild: GuildId, user_id: UserId) -> Result<bool> {
+    let guild_id = guild.get() as i64;
+    let user_id = user_id.get() as i64;
+
+    Ok(query!(
+        "SELECT user_id
+         FROM blocked_users
+         WHERE guild_id = ? AND user_id = ?",
+        guild_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .wrap_err("failed to check blocked user")?
+    .is_some())
+}
+
+/// Fetches the blocked users, most recently blocked first.
+pub async fn fetch_blocked_users(pool: &SqlitePool, guild: GuildId) -> Result<Vec<BlockedUser>> {
+    let guild_id = guild.get() as i64;
+
+    Ok(query!(
+        "SELECT user_id, reason, timestamp
+         FROM blocked_users
+         WHERE guild_id = ?
+         ORDER BY timestamp DESC",
+        guild_id
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to fetch blocked users")?
+    .into_iter()
+    .map(|row| BlockedUser {
+        user_id: UserId::new(row.user_id as u64),
+        reason: row.reason,
+        timestamp: row.timestamp,
+    })
+    .collect())
+}
+
+/// Blocks an artist from being suggested. Returns `false` if they were already blocked.
+pub async fn block_artist(
+    pool: &SqlitePool,
+    guild: GuildId,
+    artist_name: &str,
+    reason: Option<&str>,
+) -> Result<bool> {
+    let guild_id = guild.get() as i64;
+    let normalized_name = normalize_name(artist_name);
+
+    let result = query!(
+        "INSERT OR IGNORE INTO blocked_artists (guild_id, normalized_name, artist_name, reason)
+         VALUES (?, ?, ?, ?)",
+        guild_id,
+        normalized_name,
+        artist_name,
+        reason
+    )
+    .execute(pool)
+    .await
+    .wrap_err("failed to block artist")?;
+
+    Ok(result.rows_affected() != 0)
+}
+
+/// Unblocks an artist. Returns `false` if they weren't blocked.
+pub async fn unblock_artist(pool: &SqlitePool, guild: GuildId, artist_name: &str) -> Result<bool> {
+    let guild_id = guild.get() as i64;
+    let normalized_name = normalize_name(artist_name);
+
+    let result = query!(
+        "DELETE FROM blocked_artists
+         WHERE guild_id = ? AND normalized_name = ?",
+        guild_id,
+        normalized_name
+    )
+    .execute(pool)
+    .await
+    .wrap_err("failed to unblock artist")?;
+
+    Ok(result.rows_affected() != 0)
+}
+
+/// Checks whether an artist is blocked, comparing normalized names.
+pub async fn is_artist_blocked(
+    pool: &SqlitePool,
+    guild: GuildId,
+    artist_name: &str,
+) -> Result<bool> {
+    let guild_id = guild.get() as i64;
+    let normalized_name = normalize_name(artist_name);
+
+    Ok(query!(
+        "SELECT normalized_name
+         FROM blocked_artists
+         WHERE guild_id = ? AND normalized_name = ?",
+        guild_id,
+        normalized_name
+    )
+    .fetch_optional(pool)
+    .await
+    .wrap_err("failed to check blocked artist")?
+    .is_some())
+}
+
+/// Fetches the blocked artists, most recently blocked first.
+pub async fn fetch_blocked_artists(
+    pool: &SqlitePool,
+    guild: GuildId,
+) -> Result<Vec<BlockedArtist>> {
+    let guild_id = guild.get() as i64;
+
+    Ok(query!(
+        "SELECT artist_name, reason, timestamp
+         FROM blocked_artists
+         WHERE guild_id = ?
+         ORDER BY timestamp DESC",
+        guild_id
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to fetch blocked artists")?
+    .into_iter()
+    .map(|row| BlockedArtist {
+        artist_name: row.artist_name,
+        reason: row.reason,
+        timestamp: row.timestamp,
+    })
+    .collect())
+}
+
+/// Records a submission rejected because of the blocklist.
+pub async fn insert_blocked_attempt(
+    pool: &SqlitePool,
+    guild: GuildId,
+    attempt: &BlockedAttempt,
+) -> Result<()> {
+    let guild_id = guild.get() as i64;
+    let user_id = attempt.user_id.get() as i64;
+
+    query!(
+        "INSERT INTO blocked_attempts (guild_id, user_id, username, artist_name, album_name, category)
+         VALUES (?, ?, ?, ?, ?, ?)",
+        guild_id,
+        user_id,
+        attempt.username,
+        attempt.artist_name,
+        attempt.album_name,
+        attempt.category
+    )
+    .execute(pool)
+    .await
+    .wrap_err("failed to insert blocked attempt")?;
+
+    Ok(())
+}
+
+/// Fetches the recorded blocked attempts, most recent first.
+pub async fn fetch_blocked_attempts(
+    pool: &SqlitePool,
+    guild: GuildId,
+) -> Result<Vec<RecordedBlockedAttempt>> {
+    let guild_id = guild.get() as i64;
+
+    Ok(query!(
+        "SELECT user_id, username, artist_name, album_name, category, timestamp
+         FROM blocked_attempts
+         WHERE guild_id = ?
+         ORDER BY timestamp DESC, id DESC",
+        guild_id
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to fetch blocked attempts")?
+    .into_iter()
+    .map(|row| RecordedBlockedAttempt {
+        attempt: BlockedAttempt {
+            user_id: UserId::new(row.user_id as u64),
+            username: row.username,
+            artist_name: row.artist_name,
+            album_name: row.album_name,
+            category: row.category,
+        },
+        timestamp: row.timestamp,
+    })
+    .collect())
+}
+
+/// Inserts a notice for a user.
+pub async fn insert_notice(
+    pool: &SqlitePool,
+    guild: GuildId,
+    user_id: UserId,
+    content: &str,
+) -> Result<()> {
+    let guild_id = guild.get() as i64;
+    let user_id = user_id.get() as i64;
+
+    query!(
+        "INSERT INTO notices (guild_id, user_id, content)
+         VALUES (?, ?, ?)",
+        guild_id,
+        user_id,
+        content
+    )
+    .execute(pool)
+    .await
+    .wrap_err("failed to insert notice")?;
+
+    Ok(())
+}
+
+/// Removes and returns the notices for a user, oldest first.
+pub async fn take_notices(
+    pool: &SqlitePool,
+    guild: GuildId,
+    user_id: UserId,
+) -> Result<Vec<String>> {
+    let guild_id = guild.get() as i64;
+    let user_id = user_id.get() as i64;
+
+    let mut notices = query!(
+        "DELETE FROM notices
+         WHERE guild_id = ? AND user_id = ?
+         RETURNING id, content",
+        guild_id,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to remove notices")?;
+
+    notices.sort_by_key(|notice| notice.id);
+
+    Ok(notices.into_iter().map(|notice| notice.content).collect())
+}
+
 /// Inserts a new poll into the database and returns its ID.
 pub async fn insert_poll(
     pool: &SqlitePool,
+    guild: GuildId,
     message_id: MessageId,
     author_id: UserId,
-    internal: bool,
+    category: &str,
 ) -> Result<u64> {
+    let guild_id = guild.get() as i64;
     let message_id = message_id.get() as i64;
     let author_id = author_id.get() as i64;
 
     Ok(
         query!(
-            "INSERT INTO polls (message_id, author_id, internal)
-             VALUES (?, ?, ?)",
+            "INSERT INTO polls (guild_id, message_id, author_id, category)
+             VALUES (?, ?, ?, ?)",
+            guild_id,
             message_id,
             author_id,
-            internal
+            category
         )
         .execute(pool)
         .await
@@ -206,11 +1430,31 @@ pub async fn insert_poll(
     )
 }
 
-/// Fetches all polls from the database.
-pub async fn fetch_polls(pool: &SqlitePool) -> Result<Vec<Poll>> {
+/// Deletes a poll by its ID.
+pub async fn delete_poll(pool: &SqlitePool, poll_id: u64) -> Result<()> {
+    let poll_id = poll_id as i64;
+
     query!(
-        "SELECT id, message_id, author_id, internal, status, votes
-         FROM polls"
+        "DELETE FROM polls
+         WHERE id = ?",
+        poll_id
+    )
+    .execute(pool)
+    .await
+    .wrap_err("failed to delete poll")?;
+
+    Ok(())
+}
+
+/// Fetches all polls of a guild from the database.
+pub async fn fetch_polls(pool: &SqlitePool, guild: GuildId) -> Result<Vec<Poll>> {
+    let guild_id = guild.get() as i64;
+
+    query!(
+        "SELECT id, message_id, author_id, category, status, votes, downvotes
+         FROM polls
+         WHERE guild_id = ?",
+        guild_id
     )
     .fetch_all(pool)
     .await
@@ -221,8 +1465,8 @@ pub async fn fetch_polls(pool: &SqlitePool) -> Result<Vec<Poll>> {
             id: row.id as u64,
             message_id: MessageId::new(row.message_id as u64),
             author_id: UserId::new(row.author_id as u64),
-            internal: row.internal,
-            status: PollStatus::parse(row.status as u64, row.votes)?,
+            category: row.category,
+            status: PollStatus::parse(row.status as u64, row.votes, row.downvotes)?,
         })
     })
     .collect::<Result<_>>()
@@ -235,19 +1479,16 @@ pub async fn update_poll_status(
     status: &PollStatus,
 ) -> Result<()> {
     let poll_id = poll_id as i64;
-    let (status, votes) = match status {
-        PollStatus::Pending { votes } => (0, Some(votes.iter().map(|id| id.to_string()).join(","))),
-        PollStatus::Completed => (1, None),
-        PollStatus::Revoked => (2, None),
-        PollStatus::Vetoed => (3, None),
-    };
+    let (status, votes, downvotes) = status.encode();
+    let status = status as i64;
 
     query!(
         "UPDATE polls
-         SET status = ?, votes = ?
+         SET status = ?, votes = ?, downvotes = ?
          WHERE id = ?",
         status,
         votes,
+        downvotes,
         poll_id
     )
     .execute(pool)
@@ -256,3 +1497,705 @@ pub async fn update_poll_status(
 
     Ok(())
 }
+
+/// Counts the suggestions of every category and the other rows of a guild.
+pub async fn fetch_stats(pool: &SqlitePool, guild: GuildId) -> Result<Stats> {
+    let guild_id = guild.get() as i64;
+
+    let categories = query!(
+        r#"SELECT category AS "category!",
+             SUM(kind = 0) AS "pending!: i64",
+             SUM(kind = 1) AS "queued!: i64",
+             SUM(kind = 2) AS "archived!: i64"
+         FROM (
+             SELECT category, approved AS kind FROM suggestions WHERE guild_id = ?
+             UNION ALL
+             SELECT category, 2 FROM deleted_suggestions WHERE guild_id = ?
+         )
+         GROUP BY category
+         ORDER BY category"#,
+        guild_id,
+        guild_id
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to count suggestions")?
+    .into_iter()
+    .map(|row| CategoryStats {
+        category: row.category,
+        pending: row.pending as u64,
+        queued: row.queued as u64,
+        archived: row.archived as u64,
+    })
+    .collect();
+
+    let counts = query!(
+        r#"SELECT
+             (SELECT COUNT(*) FROM blocked_users WHERE guild_id = ?) AS "blocked_users!: i64",
+             (SELECT COUNT(*) FROM blocked_artists WHERE guild_id = ?) AS "blocked_artists!: i64",
+             (SELECT COUNT(*) FROM blocked_attempts WHERE guild_id = ?) AS "blocked_attempts!: i64",
+             (SELECT COUNT(*) FROM notices WHERE guild_id = ?) AS "notices!: i64",
+             (SELECT COUNT(*) FROM announcement_slots WHERE guild_id = ?) AS "slots!: i64""#,
+        guild_id,
+        guild_id,
+        guild_id,
+        guild_id,
+        guild_id
+    )
+    .fetch_one(pool)
+    .await
+    .wrap_err("failed to count rows")?;
+
+    Ok(Stats {
+        categories,
+        blocked_users: counts.blocked_users as u64,
+        blocked_artists: counts.blocked_artists as u64,
+        blocked_attempts: counts.blocked_attempts as u64,
+        notices: counts.notices as u64,
+        slots: counts.slots as u64,
+    })
+}
+
+/// Fetches the suggestions, polls and archived suggestions of a guild for an export.
+///
+/// Only the category and date filters are applied, since the status is derived from several columns.
+pub async fn fetch_export(
+    pool: &SqlitePool,
+    guild: GuildId,
+    filter: &ExportFilter,
+) -> Result<Export> {
+    let guild_id = guild.get() as i64;
+    let category = filter.category.as_deref();
+
+    // the links of all suggestions are fetched at once instead of once per suggestion
+    let mut links = query!(
+        "SELECT suggestion_links.suggestion_id, suggestion_links.url
+         FROM suggestion_links
+         JOIN suggestions ON suggestions.id = suggestion_links.suggestion_id
+         WHERE suggestions.guild_id = ?
+         ORDER BY suggestion_links.id",
+        guild_id
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to fetch suggestion links")?
+    .into_iter()
+    .into_group_map_by(|row| row.suggestion_id);
+
+    let suggestions = query!(
+        "SELECT id, user_id, username, artist_name, album_name, links, notes, category, poll_id, approved, queue_position, timestamp, approved_at
+         FROM suggestions
+         WHERE guild_id = ?
+           AND (? IS NULL OR category = ?)
+           AND (? IS NULL OR date(timestamp) >= ?)
+           AND (? IS NULL OR date(timestamp) <= ?)
+         ORDER BY timestamp",
+        guild_id,
+        category,
+        category,
+        filter.since,
+        filter.since,
+        filter.until,
+        filter.until
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to fetch suggestions")?;
+
+    let mut exported_suggestions = Vec::with_capacity(suggestions.len());
+
+    for row in suggestions {
+        // suggestions from before the links table have their links only in the `links` column
+        let links = match links.remove(&row.id) {
+            Some(links) => links.into_iter().map(|link| link.url).collect(),
+            None => parse_stored_links(&row.links)
+                .into_iter()
+                .map(|link| link.url)
+                .collect(),
+        };
+
+        exported_suggestions.push(ExportedSuggestion {
+            id: row.id as u64,
+            category: row.category,
+            status: if row.approved {
+                ExportStatus::Approved
+            } else {
+                ExportStatus::Pending
+            },
+            user_id: UserId::new(row.user_id as u64),
+            username: row.username,
+            artist_name: row.artist_name,
+            album_name: row.album_name,
+            links,
+            notes: row.notes,
+            poll_id: row.poll_id.map(|poll_id| poll_id as u64),
+            queue_position: row.queue_position.map(|position| position as u64),
+            submitted_at: row.timestamp,
+            approved_at: row.approved_at,
+        });
+    }
+
+    let polls = query!(
+        r#"SELECT polls.id, polls.message_id, polls.author_id, polls.category, polls.status, polls.votes, polls.downvotes,
+             suggestions.timestamp AS "submitted_at?"
+         FROM polls
+         LEFT JOIN suggestions ON suggestions.poll_id = polls.id
+         WHERE polls.guild_id = ?
+           AND (? IS NULL OR polls.category = ?)
+           AND (? IS NULL OR date(suggestions.timestamp) >= ?)
+           AND (? IS NULL OR date(suggestions.timestamp) <= ?)
+         ORDER BY polls.id"#,
+        guild_id,
+        category,
+        category,
+        filter.since,
+        filter.since,
+        filter.until,
+        filter.until
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to fetch polls")?
+    .into_iter()
+    .map(|row| {
+        let status = PollStatus::parse(row.status as u64, row.votes, row.downvotes)?;
+        let (upvotes, downvotes) = match &status {
+            PollStatus::Pending { upvotes, downvotes } => (
+                upvotes.iter().copied().sorted().collect(),
+                downvotes.iter().copied().sorted().collect(),
+            ),
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        Ok(ExportedPoll {
+            id: row.id as u64,
+            message_id: MessageId::new(row.message_id as u64),
+            author_id: UserId::new(row.author_id as u64),
+            category: row.category,
+            status: ExportStatus::of_poll(&status),
+            upvotes,
+            downvotes,
+            submitted_at: row.submitted_at,
+        })
+    })
+    .collect::<Result<_>>()?;
+
+    let deleted_suggestions = query!(
+        "SELECT id, user_id, username, artist_name, album_name, links, notes, category, status, reason, timestamp, archived_at
+         FROM deleted_suggestions
+         WHERE guild_id = ?
+           AND (? IS NULL OR category = ?)
+           AND (? IS NULL OR date(timestamp) >= ?)
+           AND (? IS NULL OR date(timestamp) <= ?)
+         ORDER BY timestamp",
+        guild_id,
+        category,
+        category,
+        filter.since,
+        filter.since,
+        filter.until,
+        filter.until
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to fetch archived suggestions")?
+    .into_iter()
+    .map(|row| {
+        Ok(ExportedArchivedSuggestion {
+            id: row.id as u64,
+            category: row.category,
+            status: ExportStatus::of_archived(&PollStatus::parse(row.status as u64, None, None)?),
+            user_id: UserId::new(row.user_id as u64),
+            username: row.username,
+            artist_name: row.artist_name,
+            album_name: row.album_name,
+            links: parse_stored_links(&row.links)
+                .into_iter()
+                .map(|link| link.url)
+                .collect(),
+            notes: row.notes,
+            reason: row.reason,
+            submitted_at: row.timestamp,
+            archived_at: row.archived_at,
+        })
+    })
+    .collect::<Result<_>>()?;
+
+    Ok(Export {
+        suggestions: exported_suggestions,
+        polls,
+        deleted_suggestions,
+    })
+}
+
+/// Fetches all suggestions and all archived suggestions of a guild for duplicate detection.
+///
+/// The suggestion with the ID `exclude` is left out, so that an edited suggestion isn't its own duplicate.
+pub async fn fetch_duplicate_candidates(
+    pool: &SqlitePool,
+    guild: GuildId,
+    exclude: u64,
+) -> Result<Vec<Candidate>> {
+    let guild_id = guild.get() as i64;
+    let exclude = exclude as i64;
+
+    let mut candidates = query!(
+        r#"SELECT suggestions.artist_name, suggestions.album_name, suggestions.links, suggestions.category,
+                  suggestions.approved, polls.message_id AS "message_id?"
+           FROM suggestions
+           LEFT JOIN polls ON polls.id = suggestions.poll_id
+           WHERE suggestions.guild_id = ? AND suggestions.id != ?"#,
+        guild_id,
+        exclude
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to fetch suggestions")?
+    .into_iter()
+    .map(|row| Candidate {
+        artist_name: row.artist_name,
+        album_name: row.album_name,
+        links: row.links,
+        category: row.category,
+        origin: match row.message_id {
+            Some(message_id) => Origin::Poll {
+                message_id: MessageId::new(message_id as u64),
+                approved: row.approved,
+            },
+            None => Origin::Queue,
+        },
+    })
+    .collect::<Vec<_>>();
+
+    let archived = query!(
+        "SELECT artist_name, album_name, links, category, status, timestamp
+         FROM deleted_suggestions
+         WHERE guild_id = ?",
+        guild_id
+    )
+    .fetch_all(pool)
+    .await
+    .wrap_err("failed to fetch deleted suggestions")?;
+
+    for row in archived {
+        candidates.push(Candidate {
+            artist_name: row.artist_name,
+            album_name: row.album_name,
+            links: row.links,
+            category: row.category,
+            origin: Origin::Archive {
+                status: PollStatus::parse(row.status as u64, None, None)?,
+                timestamp: row.timestamp,
+            },
+        });
+    }
+
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+
+    const GUILD: GuildId = GuildId::new(1);
+
+    /// Opens an empty in-memory database with the latest schema.
+    async fn memory_pool() -> SqlitePool {
+        // every connection to an in-memory database has its own database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().in_memory(true))
+            .await
+            .unwrap();
+
+        migrate(&pool).await.unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_remove_suggestion_and_poll() {
+        const TESTS: &[(PollStatus, Option<&str>)] = &[
+            (PollStatus::Vetoed, Some("Not an album")),
+            (PollStatus::Revoked, None),
+            (PollStatus::Expired, None),
+        ];
+
+        let pool = memory_pool().await;
+
+        for (index, (status, reason)) in TESTS.iter().enumerate() {
+            let suggestion = Suggestion {
+                id: 0,
+                user_id: UserId::new(1),
+                username: "someone".into(),
+                artist_name: "C418".into(),
+                album_name: "Volume Alpha".into(),
+                links: vec![Link::parse_stored("https://c418.bandcamp.com")],
+                notes: None,
+                category: "external".into(),
+            };
+
+            let message_id = MessageId::new(index as u64 + 1);
+            let poll_id = insert_poll(&pool, GUILD, message_id, suggestion.user_id, "external")
+                .await
+                .unwrap();
+            insert_suggestion(&pool, GUILD, &suggestion, poll_id)
+                .await
+                .unwrap();
+            update_poll_status(&pool, poll_id, status).await.unwrap();
+
+            let suggestion_id = fetch_suggestion(&pool, poll_id).await.unwrap().id;
+            remove_suggestion_and_poll(&pool, suggestion_id, *reason)
+                .await
+                .unwrap();
+
+            let archived = sqlx::query_as::<_, (i64, Option<String>)>(
+                "SELECT status, reason FROM deleted_suggestions ORDER BY id DESC LIMIT 1",
+            )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+            assert_eq!(
+                archived,
+                (status.encode().0 as i64, reason.map(String::from))
+            );
+        }
+
+        assert!(fetch_polls(&pool, GUILD).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expire_poll() {
+        let pool = memory_pool().await;
+
+        let suggestion = Suggestion {
+            id: 0,
+            user_id: UserId::new(1),
+            username: "someone".into(),
+            artist_name: "C418".into(),
+            album_name: "Volume Alpha".into(),
+            links: vec![Link::parse_stored("https://c418.bandcamp.com")],
+            notes: None,
+            category: "external".into(),
+        };
+
+        let poll_id = insert_poll(
+            &pool,
+            GUILD,
+            MessageId::new(1),
+            suggestion.user_id,
+            "external",
+        )
+        .await
+        .unwrap();
+        insert_suggestion(&pool, GUILD, &suggestion, poll_id)
+            .await
+            .unwrap();
+
+        expire_poll(&pool, poll_id).await.unwrap();
+
+        let status = sqlx::query_as::<_, (i64,)>("SELECT status FROM deleted_suggestions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(status, (PollStatus::Expired.encode().0 as i64,));
+        assert!(fetch_polls(&pool, GUILD).await.unwrap().is_empty());
+
+        // a poll that is already gone isn't expired again
+        assert!(expire_poll(&pool, poll_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restore_suggestion() {
+        // whether the suggestion is restored into a new poll, and whether it is restored into the queue
+        const TESTS: &[(bool, bool)] = &[(true, false), (false, true)];
+
+        let pool = memory_pool().await;
+
+        for (index, (with_poll, approved)) in TESTS.iter().enumerate() {
+            let suggestion = Suggestion {
+                id: 0,
+                user_id: UserId::new(1),
+                username: "someone".into(),
+                artist_name: "C418".into(),
+                album_name: "Volume Beta".into(),
+                links: vec![Link::parse_stored("https://c418.bandcamp.com")],
+                notes: None,
+                category: "external".into(),
+            };
+
+            let message_id = MessageId::new(index as u64 + 1);
+            let poll_id = insert_poll(&pool, GUILD, message_id, suggestion.user_id, "external")
+                .await
+                .unwrap();
+            insert_suggestion(&pool, GUILD, &suggestion, poll_id)
+                .await
+                .unwrap();
+            update_poll_status(&pool, poll_id, &PollStatus::Vetoed)
+                .await
+                .unwrap();
+
+            let suggestion_id = fetch_suggestion(&pool, poll_id).await.unwrap().id;
+            let archived_id = remove_suggestion_and_poll(&pool, suggestion_id, Some("Oops"))
+                .await
+                .unwrap();
+
+            let poll_id = if *with_poll {
+                let message_id = MessageId::new(index as u64 + 100);
+                Some(
+                    insert_poll(&pool, GUILD, message_id, suggestion.user_id, "external")
+                        .await
+                        .unwrap(),
+                )
+            } else {
+                None
+            };
+
+            // archived suggestions of other guilds can't be restored
+            assert!(
+                restore_suggestion(&pool, GuildId::new(2), archived_id, None, true)
+                    .await
+                    .is_err()
+            );
+
+            let restored_id = restore_suggestion(&pool, GUILD, archived_id, poll_id, *approved)
+                .await
+                .unwrap();
+
+            assert!(
+                fetch_archived_suggestion(&pool, GUILD, archived_id)
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+
+            if let Some(poll_id) = poll_id {
+                let restored = fetch_suggestion(&pool, poll_id).await.unwrap();
+                assert_eq!(restored.id, restored_id);
+                assert_eq!(restored.links[0].url, suggestion.links[0].url);
+            }
+
+            let queued = fetch_queue(&pool, GUILD, "external")
+                .await
+                .unwrap()
+                .iter()
+                .any(|entry| entry.id == restored_id);
+            assert_eq!(queued, *approved);
+        }
+    }
+
+    #[test]
+    fn test_search_query() {
+        const TESTS: &[(&str, Option<&str>)] = &[
+            ("", None),
+            (" - ", None),
+            ("c418", Some("\"c418\"*")),
+            ("Alpha  Volume", Some("\"Alpha\"* \"Volume\"*")),
+            ("\"excursions\" OR", Some("\"excursions\"* \"OR\"*")),
+        ];
+
+        for (text, expected) in TESTS {
+            assert_eq!(search_query(text).as_deref(), *expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_count_suggestions() {
+        // the waiting and the archived suggestions of the user
+        const TESTS: &[(usize, usize)] = &[(0, 0), (2, 0), (0, 1), (1, 2)];
+
+        for (waiting, archived) in TESTS {
+            let pool = memory_pool().await;
+            let user_id = UserId::new(1);
+
+            for index in 0..waiting + archived {
+                let suggestion = Suggestion {
+                    id: 0,
+                    user_id,
+                    username: "someone".into(),
+                    artist_name: format!("Artist {index}"),
+                    album_name: "Album".into(),
+                    links: vec![Link::parse_stored("https://example.com")],
+                    notes: None,
+                    category: "external".into(),
+                };
+
+                let message_id = MessageId::new(index as u64 + 1);
+                let poll_id = insert_poll(&pool, GUILD, message_id, user_id, "external")
+                    .await
+                    .unwrap();
+                insert_suggestion(&pool, GUILD, &suggestion, poll_id)
+                    .await
+                    .unwrap();
+
+                if index >= *waiting {
+                    let suggestion_id = fetch_suggestion(&pool, poll_id).await.unwrap().id;
+                    remove_suggestion_and_poll(&pool, suggestion_id, None)
+                        .await
+                        .unwrap();
+                }
+            }
+
+            // archived suggestions only count towards the cooldown
+            assert_eq!(
+                count_suggestions(&pool, GUILD, user_id, "external")
+                    .await
+                    .unwrap(),
+                *waiting
+            );
+            assert_eq!(
+                count_suggestions(&pool, GUILD, user_id, "internal")
+                    .await
+                    .unwrap(),
+                0
+            );
+            assert_eq!(
+                fetch_last_submission(&pool, GUILD, user_id)
+                    .await
+                    .unwrap()
+                    .is_some(),
+                waiting + archived > 0
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocked_artists() {
+        const TESTS: &[(&str, bool)] = &[
+            ("The Beatles", true),
+            ("beatles", true),
+            ("  THE beatles! ", true),
+            ("The Beatles Tribute", false),
+            ("C418", false),
+        ];
+
+        let pool = memory_pool().await;
+
+        assert!(
+            block_artist(&pool, GUILD, "Beatles", Some("Not ours"))
+                .await
+                .unwrap()
+        );
+        assert!(
+            !block_artist(&pool, GUILD, "the beatles", None)
+                .await
+                .unwrap()
+        );
+
+        for (artist_name, expected) in TESTS {
+            assert_eq!(
+                is_artist_blocked(&pool, GUILD, artist_name).await.unwrap(),
+                *expected
+            );
+            assert!(
+                !is_artist_blocked(&pool, GuildId::new(2), artist_name)
+                    .await
+                    .unwrap()
+            );
+        }
+
+        assert!(unblock_artist(&pool, GUILD, "THE BEATLES").await.unwrap());
+        assert!(!is_artist_blocked(&pool, GUILD, "Beatles").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_pick_suggestion() {
+        // whether each suggestion is approved, and the index of the expected pick
+        const TESTS: &[(&[bool], Option<usize>)] = &[
+            (&[], None),
+            (&[false], None),
+            (&[false, true], Some(1)),
+            (&[true, true], Some(0)),
+        ];
+
+        for (approved, expected) in TESTS {
+            let pool = memory_pool().await;
+
+            for (index, approved) in approved.iter().enumerate() {
+                let suggestion = Suggestion {
+                    id: 0,
+                    user_id: UserId::new(1),
+                    username: "someone".into(),
+                    artist_name: format!("Artist {index}"),
+                    album_name: "Album".into(),
+                    links: vec![Link::parse_stored("https://example.com")],
+                    notes: None,
+                    category: "external".into(),
+                };
+
+                let message_id = MessageId::new(index as u64 + 1);
+                let poll_id = insert_poll(&pool, GUILD, message_id, suggestion.user_id, "external")
+                    .await
+                    .unwrap();
+                insert_suggestion(&pool, GUILD, &suggestion, poll_id)
+                    .await
+                    .unwrap();
+
+                if *approved {
+                    approve_suggestion(&pool, poll_id).await.unwrap();
+                }
+            }
+
+            let picked = pick_suggestion(&pool, GUILD, "external").await.unwrap();
+            assert_eq!(
+                picked.map(|suggestion| suggestion.artist_name),
+                expected.map(|index| format!("Artist {index}"))
+            );
+            assert!(
+                pick_suggestion(&pool, GUILD, "internal")
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_read_only() {
+        let path = std::env::temp_dir().join(format!("artistic-test-{}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        let data_dir = DataDir::new(&path);
+
+        // a missing database isn't created
+        assert!(open_read_only(&data_dir).await.is_err());
+        assert!(!data_dir.database().exists());
+
+        // an unmigrated database isn't migrated
+        let pool = SqlitePool::connect_with(
+            SqliteConnectOptions::new()
+                .filename(data_dir.database())
+                .create_if_missing(true),
+        )
+        .await
+        .unwrap();
+        pool.close().await;
+        assert!(open_read_only(&data_dir).await.is_err());
+
+        connect(&data_dir).await.unwrap().close().await;
+        assert!(open_read_only(&data_dir).await.is_ok());
+
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_move_entry() {
+        const TESTS: &[(u64, Option<usize>, &[u64])] = &[
+            (3, Some(0), &[3, 1, 2, 4]),
+            (1, None, &[2, 3, 4, 1]),
+            (2, Some(2), &[1, 3, 2, 4]),
+            (4, Some(10), &[1, 2, 3, 4]),
+            (5, Some(0), &[1, 2, 3, 4]),
+        ];
+
+        for (entry, position, order) in TESTS {
+            let mut queue = vec![1, 2, 3, 4];
+            let moved = move_entry(&mut queue, entry, *position);
+
+            assert_eq!(queue, *order);
+            assert_eq!(moved, order.iter().position(|other| other == entry));
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use chrono::NaiveDateTime;
+use poise::serenity_prelude::*;
+use strsim::normalized_damerau_levenshtein;
+
+use crate::types::{PollStatus, Suggestion};
+
+/// The minimum similarity between two normalized names for them to be considered the same.
+const NAME_SIMILARITY: f64 = 0.85;
+
+/// The query parameters that identify what a link points to, like the video of a YouTube link.
+const IDENTIFYING_PARAMETERS: &[&str] = &["v", "list"];
+
+/// A stored suggestion that a new submission is compared against.
+#[derive(Clone)]
+pub struct Candidate {
+    pub artist_name: String,
+    pub album_name: String,
+    pub links: String,
+    pub category: String,
+    pub origin: Origin,
+}
+
+/// Where a candidate suggestion is stored.
+#[derive(Clone)]
+pub enum Origin {
+    /// A suggestion with an open or approved poll.
+    Poll {
+        message_id: MessageId,
+        approved: bool,
+    },
+    /// An approved suggestion without a poll.
+    Queue,
+    /// A featured, revoked, vetoed or expired suggestion from `deleted_suggestions`.
+    Archive {
+        status: PollStatus,
+        timestamp: NaiveDateTime,
+    },
+    /// An earlier row of the same bulk import.
+    Import { row: usize },
+}
+
+/// Why a candidate was considered a duplicate.
+pub enum Reason {
+    Name,
+    Link,
+}
+
+pub struct Duplicate {
+    pub candidate: Candidate,
+    pub reason: Reason,
+}
+
+impl Duplicate {
+    /// Formats the duplicate as a single line for the submitter.
+    ///
+    /// `poll_channel` returns the poll channel for the candidate's category, if the category still exists.
+    pub fn format(
+        &self,
+        guild: GuildId,
+        poll_channel: impl Fn(&str) -> Option<ChannelId>,
+    ) -> String {
+        let candidate = &self.candidate;
+        let reason = match self.reason {
+            Reason::Name => "similar name",
+            Reason::Link => "shared link",
+        };
+
+        let origin = match &candidate.origin {
+            Origin::Poll {
+                message_id,
+                approved,
+            } => {
+                let status = if *approved { "approved" } else { "open" };
+
+                match poll_channel(&candidate.category) {
+                    Some(channel) => {
+                        format!("{status} poll: {}", message_id.link(channel, Some(guild)))
+                    }
+                    None => format!("{status} poll"),
+                }
+            }
+            Origin::Queue => "queued for announcement".into(),
+            Origin::Import { row } => format!("row {row} of this import"),
+            Origin::Archive { status, timestamp } => format!(
+                "{} (submitted {})",
+                match status {
+                    PollStatus::Completed => "previously featured",
+                    PollStatus::Revoked => "previously revoked",
+                    PollStatus::Vetoed => "previously vetoed",
+                    PollStatus::Expired => "previously expired",
+                    PollStatus::Pending { .. } => "previously archived",
+                },
+                timestamp.format("%Y-%m-%d")
+            ),
+        };
+
+        format!(
+            "- **{}** by **{}** ({reason}), {origin}",
+            candidate.album_name, candidate.artist_name
+        )
+    }
+}
+
+/// Normalizes an artist or album name for comparison.
+///
+/// The name is lowercased, punctuation is dropped, whitespace is collapsed and a leading "the" is removed.
+pub fn normalize_name(name: &str) -> String {
+    let name = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>();
+
+    let words = name.split_whitespace().collect::<Vec<_>>();
+
+    match words.as_slice() {
+        ["the", rest @ ..] if !rest.is_empty() => rest.join(" "),
+        words => words.join(" "),
+    }
+}
+
+/// Normalizes a link for comparison.
+///
+/// The scheme, `www.`, fragment and trailing slashes are removed and the host is lowercased.
+/// The path keeps its case, because the IDs of some platforms are case-sensitive,
+/// and only the [`IDENTIFYING_PARAMETERS`] of the query are kept.
+pub fn normalize_link(link: &str) -> String {
+    let link = link.trim();
+    let link = link.split('#').next().unwrap_or_default();
+    let (link, query) = link.split_once('?').unwrap_or((link, ""));
+
+    let link = ["https://", "http://"]
+        .into_iter()
+        .find_map(|scheme| {
+            link.get(..scheme.len())
+                .filter(|prefix| prefix.eq_ignore_ascii_case(scheme))
+                .map(|_| &link[scheme.len()..])
+        })
+        .unwrap_or(link);
+
+    let (host, path) = link.split_once('/').unwrap_or((link, ""));
+    let host = host.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let path = path.trim_end_matches('/');
+
+    let query = query
+        .split('&')
+        .filter(|parameter| {
+            let name = parameter.split('=').next().unwrap_or_default();
+            IDENTIFYING_PARAMETERS.contains(&name)
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let mut normalized = host.to_string();
+
+    if !path.is_empty() {
+        normalized += &format!("/{path}");
+    }
+
+    if !query.is_empty() {
+        normalized += &format!("?{query}");
+    }
+
+    normalized
+}
+
+/// Returns whether two normalized names are similar enough to be considered the same.
+fn similar(a: &str, b: &str) -> bool {
+    a == b || normalized_damerau_levenshtein(a, b) >= NAME_SIMILARITY
+}
+
+/// Finds the candidates that are duplicates or near-duplicates of a suggestion.
+///
+/// A candidate is a duplicate if both its artist and album names are similar, or if it shares a link.
+pub fn find(suggestion: &Suggestion, candidates: &[Candidate]) -> Vec<Duplicate> {
+    let artist_name = normalize_name(&suggestion.artist_name);
+    let album_name = normalize_name(&suggestion.album_name);
+    let links = suggestion
+        .links
+        .iter()
+        .map(|link| normalize_link(&link.url))
+        .collect::<Vec<_>>();
+
+    candidates
+        .iter()
+        .filter_map(|candidate| {
+            let reason = if similar(&artist_name, &normalize_name(&candidate.artist_name))
+                && similar(&album_name, &normalize_name(&candidate.album_name))
+            {
+                Reason::Name
+            } else if candidate
+                .links
+                .lines()
+                .map(normalize_link)
+                .any(|link| links.contains(&link))
+            {
+                Reason::Link
+            } else {
+                return None;
+            };
+
+            Some(Duplicate {
+                candidate: candidate.clone(),
+                reason,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        const TESTS: &[(&str, &str)] = &[
+            ("C418", "c418"),
+            ("  The   Beatles ", "beatles"),
+            ("The", "the"),
+            ("Volume Alpha!", "volume alpha"),
+            ("Excursions (Deluxe)", "excursions deluxe"),
+        ];
+
+        for (name, expected) in TESTS {
+            assert_eq!(normalize_name(name), *expected);
+        }
+    }
+
+    #[test]
+    fn test_normalize_link() {
+        const TESTS: &[(&str, &str)] = &[
+            ("https://c418.bandcamp.com/", "c418.bandcamp.com"),
+            (
+                "http://www.Example.com/album?si=abc#top",
+                "example.com/album",
+            ),
+            (" open.spotify.com/album/123 ", "open.spotify.com/album/123"),
+            (
+                "https://open.spotify.com/album/4aBcD?si=xyz",
+                "open.spotify.com/album/4aBcD",
+            ),
+            (
+                "https://www.youtube.com/watch?v=a&si=xyz",
+                "youtube.com/watch?v=a",
+            ),
+            ("HTTPS://YouTube.com/watch?v=b", "youtube.com/watch?v=b"),
+            (
+                "https://youtube.com/playlist?list=PLx&index=2",
+                "youtube.com/playlist?list=PLx",
+            ),
+        ];
+
+        for (link, expected) in TESTS {
+            assert_eq!(normalize_link(link), *expected);
+        }
+
+        assert_ne!(
+            normalize_link("https://youtube.com/watch?v=a"),
+            normalize_link("https://youtube.com/watch?v=b")
+        );
+    }
+
+    #[test]
+    fn test_similar() {
+        assert!(similar("volume alpha", "volume alpha"));
+        assert!(similar("volume alpha", "volume alhpa"));
+        assert!(!similar("volume alpha", "volume beta"));
+    }
+}
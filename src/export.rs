@@ -0,0 +1,405 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use clap::ValueEnum;
+use color_eyre::{Result, eyre::Context as _};
+use itertools::Itertools;
+use poise::{ChoiceParameter, serenity_prelude::*};
+use serde::Serialize;
+
+use crate::types::PollStatus;
+
+/// The format of dates in CSV files.
+const CSV_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// The status of an exported suggestion or poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ChoiceParameter, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    /// The poll is still open.
+    #[name = "pending"]
+    Pending,
+    /// The poll passed and the suggestion waits in the queue.
+    #[name = "approved"]
+    Approved,
+    /// The suggestion was announced.
+    #[name = "featured"]
+    Featured,
+    #[name = "revoked"]
+    Revoked,
+    #[name = "vetoed"]
+    Vetoed,
+    #[name = "expired"]
+    Expired,
+}
+
+impl ExportStatus {
+    /// Returns the status of a poll whose suggestion is still in the database.
+    pub fn of_poll(status: &PollStatus) -> ExportStatus {
+        match status {
+            PollStatus::Pending { .. } => ExportStatus::Pending,
+            PollStatus::Completed => ExportStatus::Approved,
+            PollStatus::Revoked => ExportStatus::Revoked,
+            PollStatus::Vetoed => ExportStatus::Vetoed,
+            PollStatus::Expired => ExportStatus::Expired,
+        }
+    }
+
+    /// Returns the status of an archived suggestion.
+    pub fn of_archived(status: &PollStatus) -> ExportStatus {
+        match status {
+            PollStatus::Completed => ExportStatus::Featured,
+            status => ExportStatus::of_poll(status),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ExportStatus::Pending => "pending",
+            ExportStatus::Approved => "approved",
+            ExportStatus::Featured => "featured",
+            ExportStatus::Revoked => "revoked",
+            ExportStatus::Vetoed => "vetoed",
+            ExportStatus::Expired => "expired",
+        }
+    }
+}
+
+/// The file format of an export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ChoiceParameter, ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON file with every table.
+    #[default]
+    #[name = "json"]
+    Json,
+    /// One CSV file per table.
+    #[name = "csv"]
+    Csv,
+}
+
+/// Filters for exports. Unset filters match everything.
+#[derive(Default)]
+pub struct ExportFilter {
+    pub category: Option<String>,
+    pub status: Option<ExportStatus>,
+    /// The first submission date to export.
+    pub since: Option<NaiveDate>,
+    /// The last submission date to export.
+    pub until: Option<NaiveDate>,
+}
+
+impl ExportFilter {
+    /// Returns whether a row matches the filter.
+    fn matches(
+        &self,
+        category: &str,
+        status: ExportStatus,
+        submitted_at: Option<NaiveDateTime>,
+    ) -> bool {
+        let date = submitted_at.map(|submitted_at| submitted_at.date());
+
+        self.category.as_ref().is_none_or(|id| id == category)
+            && self.status.is_none_or(|filter| filter == status)
+            && self
+                .since
+                .is_none_or(|since| date.is_some_and(|date| date >= since))
+            && self
+                .until
+                .is_none_or(|until| date.is_some_and(|date| date <= until))
+    }
+}
+
+/// A row of `suggestions`.
+#[derive(Serialize)]
+pub struct ExportedSuggestion {
+    pub id: u64,
+    pub category: String,
+    pub status: ExportStatus,
+    pub user_id: UserId,
+    pub username: String,
+    pub artist_name: String,
+    pub album_name: String,
+    pub links: Vec<String>,
+    pub notes: Option<String>,
+    pub poll_id: Option<u64>,
+    pub queue_position: Option<u64>,
+    pub submitted_at: NaiveDateTime,
+    pub approved_at: Option<NaiveDateTime>,
+}
+
+/// A row of `polls` with its decoded votes.
+#[derive(Serialize)]
+pub struct ExportedPoll {
+    pub id: u64,
+    pub message_id: MessageId,
+    pub author_id: UserId,
+    pub category: String,
+    pub status: ExportStatus,
+    pub upvotes: Vec<UserId>,
+    pub downvotes: Vec<UserId>,
+    /// The submission date of the suggestion of the poll.
+    pub submitted_at: Option<NaiveDateTime>,
+}
+
+/// A row of `deleted_suggestions`.
+#[derive(Serialize)]
+pub struct ExportedArchivedSuggestion {
+    pub id: u64,
+    pub category: String,
+    pub status: ExportStatus,
+    pub user_id: UserId,
+    pub username: String,
+    pub artist_name: String,
+    pub album_name: String,
+    pub links: Vec<String>,
+    pub notes: Option<String>,
+    pub reason: Option<String>,
+    pub submitted_at: NaiveDateTime,
+    pub archived_at: Option<NaiveDateTime>,
+}
+
+/// The exported tables of a guild.
+#[derive(Serialize)]
+pub struct Export {
+    pub suggestions: Vec<ExportedSuggestion>,
+    pub polls: Vec<ExportedPoll>,
+    pub deleted_suggestions: Vec<ExportedArchivedSuggestion>,
+}
+
+/// A rendered export file.
+pub struct ExportFile {
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+impl Export {
+    /// Removes the rows that don't match a filter.
+    pub fn filter(&mut self, filter: &ExportFilter) {
+        self.suggestions
+            .retain(|row| filter.matches(&row.category, row.status, Some(row.submitted_at)));
+        self.polls
+            .retain(|row| filter.matches(&row.category, row.status, row.submitted_at));
+        self.deleted_suggestions
+            .retain(|row| filter.matches(&row.category, row.status, Some(row.submitted_at)));
+    }
+
+    /// Returns the number of exported rows.
+    pub fn len(&self) -> usize {
+        self.suggestions.len() + self.polls.len() + self.deleted_suggestions.len()
+    }
+
+    /// Renders the export as files whose names start with `prefix`.
+    pub fn render(&self, format: ExportFormat, prefix: &str) -> Result<Vec<ExportFile>> {
+        match format {
+            ExportFormat::Json => Ok(vec![ExportFile {
+                name: format!("{prefix}.json"),
+                content: serde_json::to_vec_pretty(self).wrap_err("failed to serialize export")?,
+            }]),
+            ExportFormat::Csv => Ok(vec![
+                ExportFile {
+                    name: format!("{prefix}-suggestions.csv"),
+                    content: to_csv(
+                        &SUGGESTION_HEADER,
+                        self.suggestions.iter().map(|row| {
+                            vec![
+                                row.id.to_string(),
+                                row.category.clone(),
+                                row.status.name().into(),
+                                row.user_id.to_string(),
+                                row.username.clone(),
+                                row.artist_name.clone(),
+                                row.album_name.clone(),
+                                row.links.join("\n"),
+                                row.notes.clone().unwrap_or_default(),
+                                optional(row.poll_id),
+                                optional(row.queue_position),
+                                format_date(Some(row.submitted_at)),
+                                format_date(row.approved_at),
+                            ]
+                        }),
+                    )?,
+                },
+                ExportFile {
+                    name: format!("{prefix}-polls.csv"),
+                    content: to_csv(
+                        &POLL_HEADER,
+                        self.polls.iter().map(|row| {
+                            vec![
+                                row.id.to_string(),
+                                row.message_id.to_string(),
+                                row.author_id.to_string(),
+                                row.category.clone(),
+                                row.status.name().into(),
+                                row.upvotes.iter().join(","),
+                                row.downvotes.iter().join(","),
+                                format_date(row.submitted_at),
+                            ]
+                        }),
+                    )?,
+                },
+                ExportFile {
+                    name: format!("{prefix}-deleted_suggestions.csv"),
+                    content: to_csv(
+                        &ARCHIVED_SUGGESTION_HEADER,
+                        self.deleted_suggestions.iter().map(|row| {
+                            vec![
+                                row.id.to_string(),
+                                row.category.clone(),
+                                row.status.name().into(),
+                                row.user_id.to_string(),
+                                row.username.clone(),
+                                row.artist_name.clone(),
+                                row.album_name.clone(),
+                                row.links.join("\n"),
+                                row.notes.clone().unwrap_or_default(),
+                                row.reason.clone().unwrap_or_default(),
+                                format_date(Some(row.submitted_at)),
+                                format_date(row.archived_at),
+                            ]
+                        }),
+                    )?,
+                },
+            ]),
+        }
+    }
+}
+
+const SUGGESTION_HEADER: [&str; 13] = [
+    "id",
+    "category",
+    "status",
+    "user_id",
+    "username",
+    "artist_name",
+    "album_name",
+    "links",
+    "notes",
+    "poll_id",
+    "queue_position",
+    "submitted_at",
+    "approved_at",
+];
+
+const POLL_HEADER: [&str; 8] = [
+    "id",
+    "message_id",
+    "author_id",
+    "category",
+    "status",
+    "upvotes",
+    "downvotes",
+    "submitted_at",
+];
+
+const ARCHIVED_SUGGESTION_HEADER: [&str; 12] = [
+    "id",
+    "category",
+    "status",
+    "user_id",
+    "username",
+    "artist_name",
+    "album_name",
+    "links",
+    "notes",
+    "reason",
+    "submitted_at",
+    "archived_at",
+];
+
+/// Writes a header and records as CSV.
+fn to_csv(header: &[&str], records: impl Iterator<Item = Vec<String>>) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer
+        .write_record(header)
+        .wrap_err("failed to write CSV header")?;
+
+    for record in records {
+        writer
+            .write_record(&record)
+            .wrap_err("failed to write CSV record")?;
+    }
+
+    writer.into_inner().wrap_err("failed to finish CSV")
+}
+
+/// Formats an optional number for CSV, leaving the cell empty if there is none.
+fn optional(value: Option<u64>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// Formats an optional date for CSV, leaving the cell empty if there is none.
+fn format_date(date: Option<NaiveDateTime>) -> String {
+    date.map(|date| date.format(CSV_DATE_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, CSV_DATE_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let filter = ExportFilter {
+            category: Some("external".into()),
+            status: Some(ExportStatus::Featured),
+            since: NaiveDate::from_ymd_opt(2025, 1, 1),
+            until: NaiveDate::from_ymd_opt(2025, 1, 31),
+        };
+
+        assert!(filter.matches(
+            "external",
+            ExportStatus::Featured,
+            Some(date("2025-01-31 23:59:59"))
+        ));
+        assert!(!filter.matches(
+            "internal",
+            ExportStatus::Featured,
+            Some(date("2025-01-15 12:00:00"))
+        ));
+        assert!(!filter.matches(
+            "external",
+            ExportStatus::Vetoed,
+            Some(date("2025-01-15 12:00:00"))
+        ));
+        assert!(!filter.matches(
+            "external",
+            ExportStatus::Featured,
+            Some(date("2024-12-31 23:59:59"))
+        ));
+        assert!(!filter.matches("external", ExportStatus::Featured, None));
+        assert!(ExportFilter::default().matches("internal", ExportStatus::Pending, None));
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let export = Export {
+            suggestions: Vec::new(),
+            polls: vec![ExportedPoll {
+                id: 1,
+                message_id: MessageId::new(2),
+                author_id: UserId::new(3),
+                category: "external".into(),
+                status: ExportStatus::Pending,
+                upvotes: vec![UserId::new(4), UserId::new(5)],
+                downvotes: Vec::new(),
+                submitted_at: Some(date("2025-01-15 12:00:00")),
+            }],
+            deleted_suggestions: Vec::new(),
+        };
+
+        let files = export.render(ExportFormat::Csv, "export").unwrap();
+        let polls = files
+            .iter()
+            .find(|file| file.name == "export-polls.csv")
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(&polls.content),
+            "id,message_id,author_id,category,status,upvotes,downvotes,submitted_at\n\
+             1,2,3,external,pending,\"4,5\",,2025-01-15 12:00:00\n"
+        );
+    }
+}
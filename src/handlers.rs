@@ -1,104 +1,379 @@
-use chrono::{DateTime, Datelike, Days, NaiveTime, Utc, Weekday};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
 use color_eyre::{
     Result,
     eyre::{Context as _, Report, eyre},
 };
+use itertools::Itertools;
+use notify::{RecursiveMode, Watcher};
 use poise::{FrameworkContext, FrameworkError, serenity_prelude::*};
-use tokio::{
-    fs::{self, File},
-    io::AsyncReadExt,
-    time::sleep,
+use tokio::{sync::mpsc, time::sleep};
+use tracing::{error, info};
+
+use crate::{
+    commands::BLOCKED_MESSAGE,
+    config::{Category, Config, DuplicatePolicy, MissedAnnouncementPolicy},
+    links::LinkError,
+    schedule::{Announcement, SlotStatus, missed_slots, next_slot, plan_slot},
+    types::{BlockedAttempt, Bot, Data, Poll, PollStatus, Suggestion},
 };
-use tracing::error;
-
-use crate::types::{Data, PollStatus};
-
-/// Get the next instance of `weekday` at `time` UTC, including today, from `now`.
-fn next_weekday_at(now: DateTime<Utc>, weekday: Weekday, time: NaiveTime) -> DateTime<Utc> {
-    let today_at = now.with_time(time).unwrap();
-
-    if today_at.weekday() == weekday && now < today_at {
-        // If today is the given weekday and it hasn't passed yet, use today
-        today_at
-    } else {
-        // If today is not the given weekday, or it has passed, find the next
-        let mut day_offset = weekday.days_since(today_at.weekday());
-        day_offset += (day_offset == 0) as u32 * 7; // if today is the given weekday, add a week
-        today_at + Days::new(day_offset as u64)
+
+/// Carries out the planned announcement of a category in a slot.
+///
+/// The slot is recorded as the last announcement of the category, because a deliberate skip uses up the slot too.
+/// A slot that can't be filled is not recorded, so the category is due again in the next slot.
+async fn announce(
+    ctx: &Context,
+    data: &Data,
+    config: &Config,
+    category: &Category,
+    announcement: Announcement,
+    slot: DateTime<Utc>,
+) -> Result<()> {
+    match announcement {
+        Announcement::NotDue => return Ok(()),
+        Announcement::Skipped => {
+            data.take_queue_skip(&category.id).await?;
+            info!("Skipped the {} announcement", category.id);
+        }
+        Announcement::EmptyQueue => {
+            return announce_empty_queue(ctx, data, config, category, slot).await;
+        }
+        Announcement::Post(suggestion) => {
+            data.post_announcement(ctx, config, category, &suggestion)
+                .await?;
+            warn_low_queue(ctx, data, config, category).await?;
+        }
     }
+
+    data.record_announcement(&category.id, slot).await
 }
 
-/// An infinite loop that posts internal and external artist announcements.
-pub async fn post_announcements(ctx: Context, data: Data) {
-    // get the biweekly flag from the file or create it
-    let open_result = File::options()
-        .read(true)
-        .write(true)
-        .truncate(false)
-        .create(true)
-        .open("./data/biweekly_flag.bin")
-        .await;
-
-    let mut biweekly_flag = [0];
-
-    match open_result {
-        // the error is ignored because the file is created if it doesn't exist
-        Ok(mut file) => _ = file.read_exact(&mut biweekly_flag).await,
-        Err(e) => {
-            error!("Failed to open ./data/biweekly_flag.bin: {e:#}");
+/// Posts the `empty-queue-message`, if any, and tells the facilitators that a slot couldn't be filled.
+async fn announce_empty_queue(
+    ctx: &Context,
+    data: &Data,
+    config: &Config,
+    category: &Category,
+    slot: DateTime<Utc>,
+) -> Result<()> {
+    info!("The {} queue is empty", category.id);
+
+    if let Some(message) = &config.empty_queue_message {
+        category
+            .announcement_channel
+            .send_message(ctx, CreateMessage::new().content(message))
+            .await
+            .wrap_err("failed to send message")?;
+    }
+
+    let mut content = format!(
+        "The {} queue was empty, so nothing was featured <t:{}:f>.",
+        category.name,
+        slot.timestamp()
+    );
+
+    if config.empty_queue_ping_facilitators {
+        content = format!("<@&{}> {content}", config.facilitator_role);
+    }
+
+    data.notify_facilitators(ctx, content).await
+}
+
+/// Returns whether picking a suggestion left `remaining` suggestions in a queue that had `threshold` before.
+fn crossed_low_queue_threshold(remaining: usize, threshold: usize) -> bool {
+    remaining + 1 == threshold
+}
+
+/// Tells the facilitators once fewer than `low-queue-threshold` approved suggestions remain in a queue.
+///
+/// The warning is only sent by the pick that drops the queue below the threshold, not by every pick after it.
+async fn warn_low_queue(
+    ctx: &Context,
+    data: &Data,
+    config: &Config,
+    category: &Category,
+) -> Result<()> {
+    let remaining = data.fetch_queue(&category.id).await?.len();
+
+    if !crossed_low_queue_threshold(remaining, config.low_queue_threshold) {
+        return Ok(());
+    }
+
+    data.notify_facilitators(
+        ctx,
+        format!(
+            "Only {remaining} approved suggestion(s) remain in the {} queue.",
+            category.name
+        ),
+    )
+    .await
+}
+
+/// Posts the announcements of the categories due in a slot, logging failures.
+///
+/// Returns whether every due announcement was handled.
+async fn announce_slot(ctx: &Context, data: &Data, config: &Config, slot: DateTime<Utc>) -> bool {
+    let mut handled = true;
+
+    for (category, announcement) in plan_slot(data, config, slot).await {
+        let result = match announcement {
+            Ok(announcement) => announce(ctx, data, config, category, announcement, slot).await,
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            error!("Failed to post {} announcement: {e:#}", category.id);
+            handled = false;
+        }
+    }
+
+    handled
+}
+
+/// Handles the slots that passed while the bot was offline according to `missed-announcement-policy`
+/// and tells the facilitators.
+///
+/// Only the latest missed slot is posted late. The earlier ones are recorded as skipped,
+/// and a failed late post is recorded as failed without being retried.
+async fn catch_up(ctx: &Context, data: &Data) -> Result<()> {
+    // without any handled slot, there is nothing that could have been missed
+    let Some(last_slot) = data.fetch_last_slot().await? else {
+        return Ok(());
+    };
+
+    let config = data.config();
+    let missed = missed_slots(last_slot, Utc::now(), &config);
+
+    if missed.is_empty() {
+        return Ok(());
+    }
+
+    // only the latest slot is posted late, so that a long outage doesn't empty the queues at once
+    let (latest, earlier) = missed.split_last().unwrap(); // `missed` is not empty
+
+    for slot in earlier {
+        data.record_slot(*slot, SlotStatus::Skipped).await?;
+    }
+
+    if !earlier.is_empty() {
+        info!(
+            "Skipped {} earlier missed announcement slot(s)",
+            earlier.len()
+        );
+    }
+
+    let status = match config.missed_announcement_policy {
+        MissedAnnouncementPolicy::PostLate => {
+            if announce_slot(ctx, data, &config, *latest).await {
+                SlotStatus::Late
+            } else {
+                SlotStatus::Failed
+            }
+        }
+        MissedAnnouncementPolicy::Skip => SlotStatus::Skipped,
+    };
+
+    data.record_slot(*latest, status).await?;
+
+    let slots = missed
+        .iter()
+        .map(|slot| format!("<t:{}:f>", slot.timestamp()))
+        .join(", ");
+
+    let outcome = match status {
+        SlotStatus::Late if earlier.is_empty() => "Its announcements were posted late.",
+        SlotStatus::Late => {
+            "The announcements of the last one were posted late, and the others were skipped."
+        }
+        SlotStatus::Failed => {
+            "Posting the announcements of the last one late failed, see the logs. The others were skipped."
         }
+        _ => "They were skipped.",
     };
 
-    let mut biweekly_flag = biweekly_flag[0] % 2 == 0;
+    data.notify_facilitators(
+        ctx,
+        format!(
+            "The bot was offline during {} announcement slot(s): {slots}. {outcome}",
+            missed.len()
+        ),
+    )
+    .await
+}
+
+/// An infinite loop that posts the announcements of every category of a guild.
+///
+/// The next slot is recomputed whenever the config is reloaded. A slot moved closer to the previous one
+/// doesn't post twice, because categories are only announced once they are due.
+pub async fn post_announcements(ctx: Context, data: Data) {
+    if let Err(e) = catch_up(&ctx, &data).await {
+        error!("Failed to catch up on missed announcements: {e:#}");
+    }
+
+    let mut config_changes = data.subscribe_config();
 
     loop {
         // reusing `now` because this could be called near the announcement time
         let now = Utc::now();
-        let next_date = if data.config.debug_announcement_period.is_zero() {
-            next_weekday_at(
-                now,
-                data.config.announcement_weekday,
-                data.config.announcement_time,
-            )
-        } else {
-            now + data.config.debug_announcement_period
-        };
+        let config = data.config();
+        let next_date = next_slot(now, &config);
 
         // wait until the next announcement
         // unwrapping `to_std` is safe because `next_date` is always greater than `now`
-        sleep((next_date - now).to_std().unwrap()).await;
+        tokio::select! {
+            _ = sleep((next_date - now).to_std().unwrap()) => {}
+            _ = config_changes.changed() => continue,
+        }
 
-        if let Err(e) = data.post_announcement(&ctx, false).await {
-            error!("Failed to post external announcement: {e:#}");
+        // a slot is only on time if all of its announcements were posted
+        let status = if announce_slot(&ctx, &data, &config, next_date).await {
+            SlotStatus::OnTime
+        } else {
+            SlotStatus::Failed
+        };
+
+        if let Err(e) = data.record_slot(next_date, status).await {
+            error!("Failed to record the announcement slot: {e:#}");
         }
+    }
+}
 
-        if biweekly_flag {
-            if let Err(e) = data.post_announcement(&ctx, true).await {
-                error!("Failed to post internal announcement: {e:#}");
+/// How long to wait for more changes to the config file before reloading it.
+const CONFIG_RELOAD_DELAY: Duration = Duration::from_millis(500);
+
+/// An infinite loop that reloads the config whenever the config file changes.
+///
+/// The directory is watched instead of the file, because editors often replace the file when saving it.
+pub async fn watch_config(bot: Bot) {
+    let path = bot.data_dir.config();
+    let file_name = path.file_name().map(ToOwned::to_owned);
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    let mut watcher =
+        match notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event)
+                if (event.kind.is_create() || event.kind.is_modify())
+                    && event
+                        .paths
+                        .iter()
+                        .any(|path| path.file_name() == file_name.as_deref()) =>
+            {
+                let _ = sender.send(());
+            }
+            Ok(_) => {}
+            Err(e) => error!("Failed to watch the config: {e:#}"),
+        }) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                error!("Failed to create the config watcher: {e:#}");
+                return;
             }
+        };
+
+    if let Err(e) = watcher.watch(bot.data_dir.path(), RecursiveMode::NonRecursive) {
+        error!("Failed to watch {}: {e:#}", bot.data_dir.path().display());
+        return;
+    }
+
+    info!("Watching {} for changes", path.display());
+
+    while receiver.recv().await.is_some() {
+        // saving a file can cause several events, so they are collected before reloading
+        sleep(CONFIG_RELOAD_DELAY).await;
+        while receiver.try_recv().is_ok() {}
+
+        if let Err(e) = bot.reload_config(None) {
+            error!("Failed to reload the config: {e:#}");
         }
+    }
+}
 
-        biweekly_flag ^= true;
+/// How often pending polls are checked for expiry.
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
 
-        if let Err(e) = fs::write("./data/biweekly_flag.bin", [biweekly_flag as u8])
-            .await
-            .wrap_err("failed to write to ./data/biweekly_flag.bin")
-        {
-            error!("Failed to write to ./data/biweekly_flag.bin: {e:#}");
+/// An infinite loop that expires the pending polls of a guild whose voting window has passed.
+pub async fn expire_polls(ctx: Context, data: Data) {
+    loop {
+        sleep(EXPIRY_CHECK_INTERVAL).await;
+
+        let window = data.config().poll_voting_window;
+
+        if window.is_zero() {
+            continue;
+        }
+
+        let now = Utc::now();
+        let mut polls = data.polls.lock().await;
+
+        let expired = polls
+            .extract_if(.., |poll| poll.is_expired(window, now))
+            .collect::<Vec<_>>();
+
+        drop(polls);
+
+        for poll in expired {
+            let suggestion = match data.expire_poll(poll.id).await {
+                Ok(suggestion) => suggestion,
+                Err(e) => {
+                    error!("Failed to expire poll {}: {e:#}", poll.id);
+                    // the poll is put back so that expiring it is retried in the next check
+                    data.polls.lock().await.push(poll);
+                    continue;
+                }
+            };
+
+            if let Err(e) = disable_expired_poll(&ctx, &data, &poll, &suggestion).await {
+                error!("Failed to disable expired poll {}: {e:#}", poll.id);
+            }
         }
     }
 }
 
+/// Shows an expired poll as expired and removes its buttons.
+async fn disable_expired_poll(
+    ctx: &Context,
+    data: &Data,
+    poll: &Poll,
+    suggestion: &Suggestion,
+) -> Result<()> {
+    let status = PollStatus::Expired;
+    let embed = data.build_poll_embed(&ctx, suggestion, &status).await?;
+
+    // edit the message
+    data.config()
+        .category(&poll.category)?
+        .poll_channel
+        .edit_message(
+            &ctx,
+            poll.message_id,
+            EditMessage::new()
+                .embed(embed)
+                .components(status.components()),
+        )
+        .await
+        .wrap_err("failed to edit message")?;
+
+    info!("Expired poll {}", poll.id);
+
+    Ok(())
+}
+
 pub async fn event_handler(
     ctx: &Context,
     event: &FullEvent,
-    _framework: FrameworkContext<'_, Data, Report>,
-    data: &Data,
+    _framework: FrameworkContext<'_, Bot, Report>,
+    bot: &Bot,
 ) -> Result<()> {
     if let FullEvent::InteractionCreate {
         interaction: Interaction::Component(interaction),
     } = event
     {
+        let Ok(data) = bot.guild(interaction.guild_id) else {
+            return Ok(());
+        };
+
         if let Err(e) = handle_poll_interaction(ctx, interaction, data).await {
             error!("Failed to handle poll interaction: {e:#}");
             interaction
@@ -122,11 +397,14 @@ async fn handle_poll_interaction(
     interaction: &ComponentInteraction,
     data: &Data,
 ) -> Result<()> {
+    let config = data.config();
+
     // check if the interaction is valid
     if !matches!(interaction.data.kind, ComponentInteractionDataKind::Button)
-        || interaction.guild_id != Some(data.config.guild)
-        || (interaction.channel_id != data.config.internal_poll_channel
-            && interaction.channel_id != data.config.external_poll_channel)
+        || !config
+            .categories
+            .iter()
+            .any(|category| category.poll_channel == interaction.channel_id)
         || !interaction.data.custom_id.starts_with("poll:")
     {
         return Ok(());
@@ -156,53 +434,66 @@ async fn handle_poll_interaction(
 
     // `strip_prefix` is safe because we checked that `interaction.data.custom_id` starts with "poll:"
     let response = match interaction.data.custom_id.strip_prefix("poll:").unwrap() {
-        "upvote" => {
+        action @ ("upvote" | "downvote") => {
+            let upvote = action == "upvote";
+
             if interaction.user.id != poll.author_id {
                 match &mut poll.status {
-                    PollStatus::Pending { votes } => {
-                        let inserted = votes.insert(interaction.user.id);
-
-                        // check if a new vote was added
-                        if inserted {
-                            let suggestion = data.fetch_suggestion(poll.id).await?;
-                            let mut components = None;
-
-                            // if the poll has enough votes, complete it
-                            if votes.len() >= data.config.poll_threshold {
-                                poll.status = PollStatus::Completed;
-
-                                // approve the suggestion
-                                data.approve_suggestion(poll.id).await?;
-
-                                components = Some(vec![CreateActionRow::Buttons(vec![
-                                    CreateButton::new("poll:upvote")
-                                        .label("Upvote")
-                                        .emoji('👍')
-                                        .disabled(true),
-                                    CreateButton::new("poll:revoke").label("Revoke").emoji('🗑'),
-                                    CreateButton::new("poll:veto").label("Veto").emoji('🛑'),
-                                ])]);
-                            }
+                    PollStatus::Pending { upvotes, downvotes } => {
+                        let (votes, opposing) = if upvote {
+                            (upvotes, downvotes)
+                        } else {
+                            (downvotes, upvotes)
+                        };
 
-                            data.update_poll_status(poll.id, &poll.status).await?;
+                        // voting again withdraws the vote, and voting the other way replaces it
+                        let added = !votes.remove(&interaction.user.id);
 
-                            let embed =
-                                data.build_poll_embed(&ctx, &suggestion, &poll.status).await;
-                            let mut edit_builder = EditMessage::new().embed(embed);
+                        if added {
+                            votes.insert(interaction.user.id);
+                            opposing.remove(&interaction.user.id);
+                        }
 
-                            if let Some(components) = components {
-                                edit_builder = edit_builder.components(components);
-                            }
+                        let suggestion = data.fetch_suggestion(poll.id).await?;
 
-                            // edit the message
-                            data.get_poll_channel(poll.internal)
-                                .edit_message(&ctx, poll.message_id, edit_builder)
-                                .await
-                                .wrap_err("failed to edit message")?;
+                        // if the poll has enough votes, complete it
+                        if poll
+                            .status
+                            .passes(&config, config.category(&poll.category)?)
+                        {
+                            poll.status = PollStatus::Completed;
 
-                            builder.content("Vote added!")
-                        } else {
-                            builder.content("You already voted!")
+                            // approve the suggestion
+                            data.approve_suggestion(poll.id).await?;
+                        }
+
+                        data.update_poll_status(poll.id, &poll.status).await?;
+
+                        let embed = data
+                            .build_poll_embed(&ctx, &suggestion, &poll.status)
+                            .await?;
+
+                        // edit the message
+                        config
+                            .category(&poll.category)?
+                            .poll_channel
+                            .edit_message(
+                                &ctx,
+                                poll.message_id,
+                                EditMessage::new()
+                                    .embed(embed)
+                                    .components(poll.status.components()),
+                            )
+                            .await
+                            .wrap_err("failed to edit message")?;
+
+                        match (added, upvote) {
+                            (true, true) => {
+                                builder.content("Upvote added! Press Upvote again to withdraw it.")
+                            }
+                            (true, false) => builder
+                                .content("Downvote added! Press Downvote again to withdraw it."),
+                            (false, _) => builder.content("Vote withdrawn!"),
                         }
                     }
                     PollStatus::Completed => {
@@ -210,12 +501,34 @@ async fn handle_poll_interaction(
                     }
                     PollStatus::Revoked => builder.content("This poll has been revoked!"),
                     PollStatus::Vetoed => builder.content("This poll has been vetoed!"),
+                    PollStatus::Expired => builder.content("This poll has expired!"),
                 }
             } else {
                 builder.content("You can't vote on your own poll!")
             }
         }
 
+        "edit" => {
+            if interaction.user.id == poll.author_id {
+                match poll.status {
+                    PollStatus::Pending { .. } => {
+                        // the modal can stay open for a while, so the polls are unlocked meanwhile
+                        let poll_id = poll.id;
+                        drop(polls);
+
+                        return edit_suggestion(ctx, interaction, data, poll_id).await;
+                    }
+                    PollStatus::Completed => builder
+                        .content("This suggestion has already been approved and can't be edited!"),
+                    PollStatus::Revoked => builder.content("This poll has been revoked!"),
+                    PollStatus::Vetoed => builder.content("This poll has been vetoed!"),
+                    PollStatus::Expired => builder.content("This poll has expired!"),
+                }
+            } else {
+                builder.content("Only the author of the poll can edit it!")
+            }
+        }
+
         "revoke" => {
             if interaction.user.id == poll.author_id {
                 match poll.status {
@@ -227,31 +540,22 @@ async fn handle_poll_interaction(
 
                         // remove the suggestion
                         let suggestion = data.fetch_suggestion(poll.id).await?;
-                        data.remove_suggestion_and_poll(suggestion.id).await?;
-
-                        let embed = data.build_poll_embed(&ctx, &suggestion, &poll.status).await;
-
-                        let components = vec![CreateActionRow::Buttons(vec![
-                            CreateButton::new("poll:upvote")
-                                .label("Upvote")
-                                .emoji('👍')
-                                .disabled(true),
-                            CreateButton::new("poll:revoke")
-                                .label("Revoke")
-                                .emoji('🗑')
-                                .disabled(true),
-                            CreateButton::new("poll:veto")
-                                .label("Veto")
-                                .emoji('🛑')
-                                .disabled(true),
-                        ])];
+                        data.remove_suggestion_and_poll(suggestion.id, None).await?;
+
+                        let embed = data
+                            .build_poll_embed(&ctx, &suggestion, &poll.status)
+                            .await?;
 
                         // edit the message
-                        data.get_poll_channel(poll.internal)
+                        config
+                            .category(&poll.category)?
+                            .poll_channel
                             .edit_message(
                                 &ctx,
                                 poll.message_id,
-                                EditMessage::new().embed(embed).components(components),
+                                EditMessage::new()
+                                    .embed(embed)
+                                    .components(poll.status.components()),
                             )
                             .await
                             .wrap_err("failed to edit message")?;
@@ -260,6 +564,7 @@ async fn handle_poll_interaction(
                     }
                     PollStatus::Revoked => builder.content("This poll has already been revoked!"),
                     PollStatus::Vetoed => builder.content("This poll has been vetoed!"),
+                    PollStatus::Expired => builder.content("This poll has expired!"),
                 }
             } else {
                 builder.content("Only the author of the poll can revoke it!")
@@ -269,52 +574,21 @@ async fn handle_poll_interaction(
         "veto" => {
             if interaction
                 .user
-                .has_role(&ctx, data.config.guild, data.config.facilitator_role)
+                .has_role(&ctx, data.guild, config.facilitator_role)
                 .await
                 .wrap_err("failed to check facilitator role")?
             {
                 match poll.status {
                     PollStatus::Pending { .. } | PollStatus::Completed => {
-                        // remove the poll
-                        poll.status = PollStatus::Vetoed;
-                        let poll = polls.remove(poll_index);
-                        data.update_poll_status(poll.id, &poll.status).await?;
-
-                        // remove the suggestion
-                        let suggestion = data.fetch_suggestion(poll.id).await?;
-                        data.remove_suggestion_and_poll(suggestion.id).await?;
-
-                        let embed = data.build_poll_embed(&ctx, &suggestion, &poll.status).await;
-
-                        let components = vec![CreateActionRow::Buttons(vec![
-                            CreateButton::new("poll:upvote")
-                                .label("Upvote")
-                                .emoji('👍')
-                                .disabled(true),
-                            CreateButton::new("poll:revoke")
-                                .label("Revoke")
-                                .emoji('🗑')
-                                .disabled(true),
-                            CreateButton::new("poll:veto")
-                                .label("Veto")
-                                .emoji('🛑')
-                                .disabled(true),
-                        ])];
-
-                        // edit the message
-                        data.get_poll_channel(poll.internal)
-                            .edit_message(
-                                &ctx,
-                                poll.message_id,
-                                EditMessage::new().embed(embed).components(components),
-                            )
-                            .await
-                            .wrap_err("failed to edit message")?;
+                        // the modal can stay open for a while, so the polls are unlocked meanwhile
+                        let poll_id = poll.id;
+                        drop(polls);
 
-                        builder.content("Poll vetoed!")
+                        return veto_poll(ctx, interaction, data, poll_id).await;
                     }
                     PollStatus::Revoked => builder.content("This poll has been revoked!"),
                     PollStatus::Vetoed => builder.content("This poll has already been vetoed!"),
+                    PollStatus::Expired => builder.content("This poll has expired!"),
                 }
             } else {
                 builder.content("Only designated facilitators can veto polls!")
@@ -337,7 +611,328 @@ async fn handle_poll_interaction(
     Ok(())
 }
 
-pub async fn error_handler(err: FrameworkError<'_, Data, Report>) {
+/// Lets the author edit the suggestion of a pending poll through the suggestion modal and updates the poll.
+async fn edit_suggestion(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+    poll_id: u64,
+) -> Result<()> {
+    let config = data.config();
+    let suggestion = data.fetch_suggestion(poll_id).await?;
+
+    // the modal has to be the first response, so the author is checked before it
+    if data.is_user_blocked(interaction.user.id).await? {
+        interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .ephemeral(true)
+                        .content(BLOCKED_MESSAGE),
+                ),
+            )
+            .await
+            .wrap_err("failed to send response")?;
+
+        data.record_blocked_attempt(&BlockedAttempt {
+            user_id: interaction.user.id,
+            username: interaction.user.name.clone(),
+            artist_name: None,
+            album_name: None,
+            category: suggestion.category.clone(),
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let response = interaction
+        .quick_modal(
+            ctx,
+            Suggestion::modal(
+                "Edit your suggestion".into(),
+                config.form_timeout,
+                Some(&suggestion),
+            ),
+        )
+        .await?;
+
+    let Some(response) = response else {
+        return Ok(());
+    };
+
+    let builder = CreateInteractionResponseMessage::new().ephemeral(true);
+
+    let edited = match Suggestion::parse_response(
+        &response,
+        &suggestion.category,
+        &config.link_host_denylist,
+    ) {
+        Ok(edited) => Suggestion {
+            id: suggestion.id,
+            ..edited
+        },
+        Err(e) => {
+            // invalid links are the author's mistake, so they are told what went wrong
+            let Some(e) = e.downcast_ref::<LinkError>() else {
+                return Err(e.wrap_err("failed to parse form response"));
+            };
+
+            response
+                .interaction
+                .create_response(
+                    &ctx,
+                    CreateInteractionResponse::Message(builder.content(e.to_string())),
+                )
+                .await
+                .wrap_err("failed to send response")?;
+            return Ok(());
+        }
+    };
+
+    // check the blocklist, since the artist may have been changed to a blocked one
+    if data.is_artist_blocked(&edited.artist_name).await? {
+        data.record_blocked_attempt(&BlockedAttempt {
+            user_id: edited.user_id,
+            username: edited.username.clone(),
+            artist_name: Some(edited.artist_name.clone()),
+            album_name: Some(edited.album_name.clone()),
+            category: edited.category.clone(),
+        })
+        .await?;
+
+        response
+            .interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::Message(builder.content(BLOCKED_MESSAGE)),
+            )
+            .await
+            .wrap_err("failed to send response")?;
+        return Ok(());
+    }
+
+    // check for duplicates of the other suggestions
+    let duplicates = data
+        .find_duplicates(&edited)
+        .await
+        .wrap_err("failed to find duplicates")?;
+    let duplicates = (!duplicates.is_empty()).then(|| data.format_duplicates(&duplicates));
+
+    if let Some(duplicates) = &duplicates
+        && config.duplicate_policy == DuplicatePolicy::Block
+    {
+        response
+            .interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::Message(builder.content(format!(
+                    "This edit looks like a duplicate of:\n{duplicates}\n\nYour suggestion was not updated."
+                ))),
+            )
+            .await
+            .wrap_err("failed to send response")?;
+        return Ok(());
+    }
+
+    let mut polls = data.polls.lock().await;
+
+    // the poll may have been revoked, vetoed or completed while the modal was open
+    let Some(poll) = polls
+        .iter_mut()
+        .find(|poll| poll.id == poll_id && matches!(poll.status, PollStatus::Pending { .. }))
+    else {
+        response
+            .interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::Message(
+                    builder.content("This poll is no longer open for edits."),
+                ),
+            )
+            .await
+            .wrap_err("failed to send response")?;
+        return Ok(());
+    };
+
+    data.update_suggestion(suggestion.id, &edited).await?;
+
+    if config.edit_resets_votes
+        && let PollStatus::Pending { upvotes, downvotes } = &mut poll.status
+    {
+        upvotes.clear();
+        downvotes.clear();
+        data.update_poll_status(poll.id, &poll.status).await?;
+    }
+
+    let embed = data.build_poll_embed(&ctx, &edited, &poll.status).await?;
+
+    // edit the message
+    config
+        .category(&poll.category)?
+        .poll_channel
+        .edit_message(
+            &ctx,
+            poll.message_id,
+            EditMessage::new()
+                .embed(embed)
+                .components(poll.status.components()),
+        )
+        .await
+        .wrap_err("failed to edit message")?;
+
+    let content = match duplicates {
+        Some(duplicates) => {
+            format!("Suggestion updated! Note that it looks like a duplicate of:\n{duplicates}")
+        }
+        None => "Suggestion updated!".to_string(),
+    };
+
+    response
+        .interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::Message(builder.content(content)),
+        )
+        .await
+        .wrap_err("failed to send response")?;
+
+    Ok(())
+}
+
+/// Asks the facilitator for a reason, vetoes the poll and notifies the author.
+async fn veto_poll(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+    poll_id: u64,
+) -> Result<()> {
+    let config = data.config();
+    let response = interaction
+        .quick_modal(
+            ctx,
+            CreateQuickModal::new("Veto this suggestion")
+                .field(
+                    CreateInputText::new(InputTextStyle::Paragraph, "Reason", "")
+                        .placeholder("Why the suggestion is vetoed. This is sent to its author.")
+                        .max_length(1024),
+                )
+                .timeout(config.form_timeout),
+        )
+        .await?;
+
+    let Some(response) = response else {
+        return Ok(());
+    };
+
+    let reason = response
+        .inputs
+        .first()
+        .ok_or(eyre!("invalid form structure"))?
+        .trim()
+        .to_string();
+    let builder = CreateInteractionResponseMessage::new().ephemeral(true);
+
+    // a reason of only whitespace passes the modal's check
+    if reason.is_empty() {
+        response
+            .interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::Message(
+                    builder.content("The reason can't be empty. The poll was not vetoed."),
+                ),
+            )
+            .await
+            .wrap_err("failed to send response")?;
+        return Ok(());
+    }
+
+    let mut polls = data.polls.lock().await;
+
+    // the poll may have been revoked or vetoed while the modal was open
+    let Some(poll_index) = polls.iter().position(|poll| {
+        poll.id == poll_id
+            && matches!(
+                poll.status,
+                PollStatus::Pending { .. } | PollStatus::Completed
+            )
+    }) else {
+        response
+            .interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::Message(
+                    builder.content("This poll is no longer open for edits."),
+                ),
+            )
+            .await
+            .wrap_err("failed to send response")?;
+        return Ok(());
+    };
+
+    // remove the poll
+    let mut poll = polls.remove(poll_index);
+    poll.status = PollStatus::Vetoed;
+    data.update_poll_status(poll.id, &poll.status).await?;
+
+    // remove the suggestion
+    let suggestion = data.fetch_suggestion(poll.id).await?;
+    let archived_id = data
+        .remove_suggestion_and_poll(suggestion.id, Some(&reason))
+        .await?;
+
+    let mut embed = data
+        .build_poll_embed(&ctx, &suggestion, &poll.status)
+        .await?;
+
+    if config.show_veto_reasons {
+        embed = embed.field("Veto Reason", &reason, false);
+    }
+
+    // edit the message
+    config
+        .category(&poll.category)?
+        .poll_channel
+        .edit_message(
+            &ctx,
+            poll.message_id,
+            EditMessage::new()
+                .embed(embed)
+                .components(poll.status.components()),
+        )
+        .await
+        .wrap_err("failed to edit message")?;
+
+    drop(polls);
+
+    response
+        .interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::Message(builder.content(format!(
+                "Poll vetoed! Use `/restore {archived_id}` to undo this."
+            ))),
+        )
+        .await
+        .wrap_err("failed to send response")?;
+
+    // tell the author why their suggestion was vetoed
+    data.notify(
+        ctx,
+        suggestion.user_id,
+        format!(
+            "Your suggestion of **{}** by **{}** was vetoed by a facilitator.\nReason: {reason}",
+            suggestion.album_name, suggestion.artist_name
+        ),
+    )
+    .await
+    .wrap_err("failed to notify the author")?;
+
+    Ok(())
+}
+
+pub async fn error_handler(err: FrameworkError<'_, Bot, Report>) {
     match err {
         FrameworkError::Setup { error, .. } => error!("Setup error: {error:#}"),
         FrameworkError::EventHandler { error, .. } => error!("Event handler error: {error:#}"),
@@ -360,33 +955,25 @@ pub async fn error_handler(err: FrameworkError<'_, Data, Report>) {
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
-
-    use chrono::NaiveDateTime;
-
     use super::*;
 
-    fn parse_date(date: &str) -> NaiveDateTime {
-        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap()
-    }
-
     #[test]
-    fn test_next_weekday_at() {
-        const TESTS: &[(&str, &str, &str)] = &[
-            ("2025-01-01 00:00:00", "mon 00:00:00", "2025-01-06 00:00:00"),
-            ("2025-01-02 00:00:00", "mon 00:00:00", "2025-01-06 00:00:00"),
-            ("2025-01-01 11:59:59", "wed 12:00:00", "2025-01-01 12:00:00"),
-            ("2025-01-01 12:00:00", "wed 12:00:00", "2025-01-08 12:00:00"),
+    fn test_crossed_low_queue_threshold() {
+        // the suggestions remaining after a pick, the threshold, and whether the pick crossed it
+        const TESTS: &[(usize, usize, bool)] = &[
+            (0, 0, false),
+            (5, 0, false),
+            (3, 3, false),
+            (2, 3, true),
+            (1, 3, false),
+            (0, 1, true),
         ];
 
-        for (now, weekday_time, expected) in TESTS {
-            let now = parse_date(now).and_utc();
-            let weekday = Weekday::from_str(&weekday_time[..3]).unwrap();
-            let time = NaiveTime::parse_from_str(&weekday_time[4..], "%H:%M:%S").unwrap();
-            let actual = next_weekday_at(now, weekday, time);
-            let expected = parse_date(expected).and_utc();
-
-            assert_eq!(actual, expected);
+        for (remaining, threshold, expected) in TESTS {
+            assert_eq!(
+                crossed_low_queue_threshold(*remaining, *threshold),
+                *expected
+            );
         }
     }
 }
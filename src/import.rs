@@ -0,0 +1,319 @@
+use std::path::Path;
+
+use color_eyre::{
+    Result,
+    eyre::{Context as _, eyre},
+};
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    duplicates::{self, Candidate, Origin},
+    links::join_links,
+    types::{Data, Suggestion},
+};
+
+/// A suggestion to import, in the format of the `suggestions` rows of an export.
+///
+/// Other columns, like the ID and status of exported rows, are ignored.
+#[derive(Deserialize)]
+pub struct ImportRow {
+    pub artist_name: String,
+    pub album_name: String,
+    /// The links, either one per line or as a JSON array.
+    #[serde(deserialize_with = "deserialize_links")]
+    pub links: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// The category ID. The default category of the import is used if there is none.
+    #[serde(default)]
+    pub category: Option<String>,
+    /// The original submitter.
+    pub user_id: UserId,
+    pub username: String,
+}
+
+/// Deserializes links given as a string or as an array of strings, one link per line.
+fn deserialize_links<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Links {
+        Text(String),
+        List(Vec<String>),
+    }
+
+    Ok(match Links::deserialize(deserializer)? {
+        Links::Text(links) => links,
+        Links::List(links) => links.join("\n"),
+    })
+}
+
+/// Parses the rows of a CSV or JSON file, depending on the extension of its name.
+///
+/// JSON files hold an array of rows or an export with a `suggestions` array.
+/// Rows that can't be parsed are returned as errors so that the other rows can still be imported.
+pub fn parse_rows(name: &str, content: &[u8]) -> Result<Vec<Result<ImportRow, String>>> {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+
+    match extension.as_deref() {
+        Some("csv") => Ok(csv::Reader::from_reader(content)
+            .deserialize()
+            .map(|row| row.map_err(|e| e.to_string()))
+            .collect()),
+        Some("json") => {
+            let value = serde_json::from_slice::<serde_json::Value>(content)
+                .wrap_err_with(|| format!("{name} is not valid JSON"))?;
+
+            let rows = match value {
+                serde_json::Value::Array(rows) => rows,
+                serde_json::Value::Object(mut export) => match export.remove("suggestions") {
+                    Some(serde_json::Value::Array(rows)) => rows,
+                    _ => return Err(eyre!("{name} has no `suggestions` array")),
+                },
+                _ => return Err(eyre!("{name} is neither an array nor an export")),
+            };
+
+            Ok(rows
+                .into_iter()
+                .map(|row| serde_json::from_value(row).map_err(|e| e.to_string()))
+                .collect())
+        }
+        _ => Err(eyre!("{name} is neither a CSV nor a JSON file")),
+    }
+}
+
+/// Where imported suggestions go.
+pub enum ImportTarget<'a> {
+    /// Straight into the approved queue of their category.
+    Queue,
+    /// Into a new poll each.
+    Poll(&'a Context),
+}
+
+pub struct ImportOptions {
+    /// The category of rows without one.
+    pub category: Option<String>,
+    /// Only report what would be imported.
+    pub dry_run: bool,
+    /// Skip rows that look like duplicates of stored suggestions or of earlier rows.
+    pub skip_duplicates: bool,
+}
+
+/// The outcome of an import, with one line per row.
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub lines: Vec<String>,
+}
+
+impl ImportReport {
+    /// Returns the number of rows of each outcome as one sentence.
+    pub fn summary(&self) -> String {
+        let verb = if self.dry_run {
+            "Would import"
+        } else {
+            "Imported"
+        };
+
+        format!(
+            "{verb} {} suggestion(s), skipped {} and failed to import {}.",
+            self.imported, self.skipped, self.failed
+        )
+    }
+
+    fn fail(&mut self, row: usize, reason: impl std::fmt::Display) {
+        self.failed += 1;
+        self.lines.push(format!("row {row}: failed, {reason}"));
+    }
+
+    fn skip(&mut self, row: usize, suggestion: &Suggestion, reason: impl std::fmt::Display) {
+        self.skipped += 1;
+        self.lines.push(format!(
+            "row {row}: skipped {} by {}, {reason}",
+            suggestion.album_name, suggestion.artist_name
+        ));
+    }
+}
+
+/// Imports suggestions into a guild, validating every row like a `/suggest` submission.
+///
+/// Rows by blocked users or of blocked artists are skipped.
+/// A row that fails doesn't stop the import of the following rows.
+pub async fn import(
+    data: &Data,
+    rows: Vec<Result<ImportRow, String>>,
+    target: ImportTarget<'_>,
+    options: &ImportOptions,
+) -> Result<ImportReport> {
+    let config = data.config();
+    let mut report = ImportReport {
+        dry_run: options.dry_run,
+        imported: 0,
+        skipped: 0,
+        failed: 0,
+        lines: Vec::new(),
+    };
+
+    // the accepted rows are added to the candidates, to find duplicates within the file too
+    let mut candidates = if options.skip_duplicates {
+        data.fetch_duplicate_candidates().await?
+    } else {
+        Vec::new()
+    };
+
+    for (index, row) in rows.into_iter().enumerate() {
+        let number = index + 1;
+
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                report.fail(number, e);
+                continue;
+            }
+        };
+
+        let Some(category) = row
+            .category
+            .filter(|category| !category.is_empty())
+            .or_else(|| options.category.clone())
+        else {
+            report.fail(number, "no category is given");
+            continue;
+        };
+
+        if config.category(&category).is_err() {
+            report.fail(number, format!("there is no category `{category}`"));
+            continue;
+        }
+
+        let inputs = [
+            row.artist_name,
+            row.album_name,
+            row.links,
+            row.notes.unwrap_or_default(),
+        ];
+
+        let suggestion = match Suggestion::parse(
+            row.user_id,
+            row.username,
+            &inputs,
+            &category,
+            &config.link_host_denylist,
+        ) {
+            Ok(suggestion) => suggestion,
+            Err(e) => {
+                report.fail(number, e);
+                continue;
+            }
+        };
+
+        // check the blocklist
+        if data.is_user_blocked(suggestion.user_id).await? {
+            report.skip(number, &suggestion, "the submitter is blocked");
+            continue;
+        }
+
+        if data.is_artist_blocked(&suggestion.artist_name).await? {
+            report.skip(number, &suggestion, "the artist is blocked");
+            continue;
+        }
+
+        // check for duplicates
+        if options.skip_duplicates {
+            let duplicates = duplicates::find(&suggestion, &candidates);
+
+            if !duplicates.is_empty() {
+                report.skip(
+                    number,
+                    &suggestion,
+                    format!(
+                        "it looks like a duplicate of:\n{}",
+                        data.format_duplicates(&duplicates)
+                    ),
+                );
+                continue;
+            }
+        }
+
+        // import the suggestion
+        let outcome = if options.dry_run {
+            Ok("would import".to_string())
+        } else {
+            match target {
+                ImportTarget::Queue => data
+                    .insert_queued_suggestion(&suggestion)
+                    .await
+                    .map(|suggestion_id| format!("queued as #{suggestion_id}")),
+                // the submitters aren't pinged, since an import may open hundreds of polls
+                ImportTarget::Poll(ctx) => data
+                    .open_poll(ctx, &suggestion, false)
+                    .await
+                    .map(|poll_id| format!("opened poll #{poll_id}")),
+            }
+        };
+
+        match outcome {
+            Ok(outcome) => {
+                report.imported += 1;
+                report.lines.push(format!(
+                    "row {number}: {outcome}, {} by {} in `{category}`, submitted by {} ({})",
+                    suggestion.album_name,
+                    suggestion.artist_name,
+                    suggestion.username,
+                    suggestion.user_id
+                ));
+
+                if options.skip_duplicates {
+                    candidates.push(Candidate {
+                        artist_name: suggestion.artist_name,
+                        album_name: suggestion.album_name,
+                        links: join_links(&suggestion.links),
+                        category: suggestion.category,
+                        origin: Origin::Import { row: number },
+                    });
+                }
+            }
+            Err(e) => report.fail(number, format!("{e:#}")),
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rows() {
+        let csv = "artist_name,album_name,links,user_id,username,category\n\
+                   C418,Volume Alpha,\"https://c418.bandcamp.com\nhttps://example.com\",123,someone,\n\
+                   C418,Volume Beta\n";
+        let rows = parse_rows("suggestions.CSV", csv.as_bytes()).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        let row = rows[0].as_ref().unwrap();
+        assert_eq!(row.links, "https://c418.bandcamp.com\nhttps://example.com");
+        assert_eq!(row.user_id, UserId::new(123));
+        assert_eq!(row.category, None);
+        assert_eq!(row.notes, None);
+        assert!(rows[1].is_err());
+
+        let json = r#"{"suggestions": [{"id": 1, "artist_name": "C418", "album_name": "Volume Alpha",
+                       "links": ["https://c418.bandcamp.com", "https://example.com"],
+                       "notes": null, "user_id": "123", "username": "someone"}]}"#;
+        let rows = parse_rows("export.json", json.as_bytes()).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        let row = rows[0].as_ref().unwrap();
+        assert_eq!(row.links, "https://c418.bandcamp.com\nhttps://example.com");
+        assert_eq!(row.user_id, UserId::new(123));
+
+        assert!(parse_rows("suggestions.txt", b"").is_err());
+    }
+}
@@ -0,0 +1,315 @@
+use std::{error::Error, fmt};
+
+use itertools::Itertools;
+use url::{ParseError, Url};
+
+/// The maximum length of an embed field value.
+const MAX_FIELD_LENGTH: usize = 1024;
+
+/// A music platform recognized from a link's host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Bandcamp,
+    Spotify,
+    YouTube,
+    SoundCloud,
+    AppleMusic,
+    Other,
+}
+
+impl Platform {
+    /// Recognizes the platform of a host.
+    pub fn detect(host: &str) -> Platform {
+        let host = host.strip_prefix("www.").unwrap_or(host);
+        let is = |domain: &str| host == domain || host.ends_with(&format!(".{domain}"));
+
+        if is("bandcamp.com") {
+            Platform::Bandcamp
+        } else if is("spotify.com") || is("spotify.link") {
+            Platform::Spotify
+        } else if is("youtube.com") || is("youtu.be") {
+            Platform::YouTube
+        } else if is("soundcloud.com") {
+            Platform::SoundCloud
+        } else if is("music.apple.com") || is("itunes.apple.com") {
+            Platform::AppleMusic
+        } else {
+            Platform::Other
+        }
+    }
+
+    /// Returns the identifier stored in the database.
+    pub fn id(self) -> &'static str {
+        match self {
+            Platform::Bandcamp => "bandcamp",
+            Platform::Spotify => "spotify",
+            Platform::YouTube => "youtube",
+            Platform::SoundCloud => "soundcloud",
+            Platform::AppleMusic => "apple-music",
+            Platform::Other => "other",
+        }
+    }
+
+    /// Parses an identifier stored in the database, falling back to [`Platform::Other`].
+    pub fn from_id(id: &str) -> Platform {
+        match id {
+            "bandcamp" => Platform::Bandcamp,
+            "spotify" => Platform::Spotify,
+            "youtube" => Platform::YouTube,
+            "soundcloud" => Platform::SoundCloud,
+            "apple-music" => Platform::AppleMusic,
+            _ => Platform::Other,
+        }
+    }
+
+    /// Returns the display name of the platform.
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Bandcamp => "Bandcamp",
+            Platform::Spotify => "Spotify",
+            Platform::YouTube => "YouTube",
+            Platform::SoundCloud => "SoundCloud",
+            Platform::AppleMusic => "Apple Music",
+            Platform::Other => "Link",
+        }
+    }
+}
+
+/// A link to an album.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub url: String,
+    pub platform: Platform,
+}
+
+/// An error in a submitted link, shown to the submitter.
+#[derive(Debug)]
+pub enum LinkError {
+    /// No links were submitted.
+    Empty,
+    /// The line is not a valid web link.
+    Invalid(String),
+    /// The link's host is on the denylist.
+    Denied(String),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::Empty => write!(f, "Please provide at least one link."),
+            LinkError::Invalid(line) => write!(
+                f,
+                "`{line}` is not a valid link. Please put each link on its own line."
+            ),
+            LinkError::Denied(host) => write!(f, "Links to `{host}` are not allowed."),
+        }
+    }
+}
+
+impl Error for LinkError {}
+
+impl Link {
+    /// Parses and validates a single submitted link.
+    ///
+    /// Links without a scheme are assumed to be `https`. Hosts on the `denylist` (and their subdomains) are rejected.
+    pub fn parse(line: &str, denylist: &[String]) -> Result<Link, LinkError> {
+        let line = line.trim();
+
+        let url = match Url::parse(line) {
+            Err(ParseError::RelativeUrlWithoutBase) => Url::parse(&format!("https://{line}")),
+            result => result,
+        }
+        .map_err(|_| LinkError::Invalid(line.to_string()))?;
+
+        let host = match (url.scheme(), url.host_str()) {
+            ("http" | "https", Some(host)) if host.contains('.') => host.to_lowercase(),
+            _ => return Err(LinkError::Invalid(line.to_string())),
+        };
+
+        if denylist.iter().any(|denied| {
+            let denied = denied.trim().to_lowercase();
+            host == denied || host.ends_with(&format!(".{denied}"))
+        }) {
+            return Err(LinkError::Denied(host));
+        }
+
+        Ok(Link {
+            platform: Platform::detect(&host),
+            url: url.into(),
+        })
+    }
+
+    /// Creates a link from a previously stored line without validating it.
+    ///
+    /// This is used for suggestions stored before links were validated.
+    pub fn parse_stored(line: &str) -> Link {
+        let platform = Url::parse(line.trim())
+            .ok()
+            .and_then(|url| url.host_str().map(Platform::detect))
+            .unwrap_or(Platform::Other);
+
+        Link {
+            url: line.trim().to_string(),
+            platform,
+        }
+    }
+}
+
+/// Parses and validates newline-separated submitted links.
+pub fn parse_links(text: &str, denylist: &[String]) -> Result<Vec<Link>, LinkError> {
+    let links = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Link::parse(line, denylist))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if links.is_empty() {
+        return Err(LinkError::Empty);
+    }
+
+    Ok(links)
+}
+
+/// Parses the newline-separated links stored in the `links` column without validating them, skipping blank lines.
+pub fn parse_stored_links(text: &str) -> Vec<Link> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(Link::parse_stored)
+        .collect()
+}
+
+/// Joins the links into the newline-separated form stored in the `links` column.
+pub fn join_links(links: &[Link]) -> String {
+    links.iter().map(|link| &link.url).join("\n")
+}
+
+/// Formats the links as one labeled line per link for an embed field.
+///
+/// Falls back to the bare links if the labeled lines don't fit into a field,
+/// and leaves out trailing links if even those don't fit.
+pub fn format_links(links: &[Link]) -> String {
+    let labeled = links
+        .iter()
+        .map(|link| format!("**{}:** {}", link.platform.name(), link.url))
+        .join("\n");
+
+    if labeled.chars().count() <= MAX_FIELD_LENGTH {
+        return labeled;
+    }
+
+    // links are never cut, since a partial URL may still be clickable but lead somewhere else
+    (0..=links.len())
+        .rev()
+        .map(|shown| {
+            let mut formatted = join_links(&links[..shown]);
+
+            if shown < links.len() {
+                if shown > 0 {
+                    formatted.push('\n');
+                }
+                formatted.push_str(&format!("…and {} more", links.len() - shown));
+            }
+
+            formatted
+        })
+        .find(|formatted| formatted.chars().count() <= MAX_FIELD_LENGTH)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_link() {
+        const TESTS: &[(&str, &str, Platform)] = &[
+            (
+                "https://c418.bandcamp.com/album/excursions",
+                "https://c418.bandcamp.com/album/excursions",
+                Platform::Bandcamp,
+            ),
+            (
+                "open.spotify.com/album/123",
+                "https://open.spotify.com/album/123",
+                Platform::Spotify,
+            ),
+            (
+                " https://youtu.be/abc ",
+                "https://youtu.be/abc",
+                Platform::YouTube,
+            ),
+            (
+                "https://www.youtube.com/watch?v=abc",
+                "https://www.youtube.com/watch?v=abc",
+                Platform::YouTube,
+            ),
+            (
+                "https://soundcloud.com/c418",
+                "https://soundcloud.com/c418",
+                Platform::SoundCloud,
+            ),
+            (
+                "https://music.apple.com/album/1",
+                "https://music.apple.com/album/1",
+                Platform::AppleMusic,
+            ),
+            ("http://example.com", "http://example.com/", Platform::Other),
+        ];
+
+        for (line, url, platform) in TESTS {
+            let link = Link::parse(line, &[]).unwrap();
+            assert_eq!(link.url, *url);
+            assert_eq!(link.platform, *platform);
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_link() {
+        for line in [
+            "not a link",
+            "ftp://example.com",
+            "localhost",
+            "mailto:a@b.c",
+        ] {
+            assert!(matches!(Link::parse(line, &[]), Err(LinkError::Invalid(_))));
+        }
+
+        let denylist = ["example.com".to_string()];
+        assert!(matches!(
+            Link::parse("https://cdn.Example.com/a", &denylist),
+            Err(LinkError::Denied(_))
+        ));
+        assert!(Link::parse("https://notexample.com/a", &denylist).is_ok());
+    }
+
+    #[test]
+    fn test_parse_links() {
+        assert!(matches!(parse_links(" \n\n", &[]), Err(LinkError::Empty)));
+        assert_eq!(parse_links("a.com\n\nb.com\n", &[]).unwrap().len(), 2);
+        assert_eq!(parse_stored_links("a.com\n \nb.com\n").len(), 2);
+        assert!(parse_stored_links("").is_empty());
+    }
+
+    #[test]
+    fn test_format_links() {
+        let links = parse_links("https://c418.bandcamp.com\nhttps://youtu.be/abc", &[]).unwrap();
+        assert_eq!(
+            format_links(&links),
+            "**Bandcamp:** https://c418.bandcamp.com/\n**YouTube:** https://youtu.be/abc"
+        );
+
+        let text = (0..40)
+            .map(|i| format!("https://example.com/{i:0>40}"))
+            .join("\n");
+        let links = parse_links(&text, &[]).unwrap();
+        let formatted = format_links(&links);
+        let lines = formatted.lines().collect::<Vec<_>>();
+
+        assert!(formatted.chars().count() <= MAX_FIELD_LENGTH);
+        assert_eq!(
+            lines[..lines.len() - 1],
+            text.lines().collect::<Vec<_>>()[..16]
+        );
+        assert_eq!(lines[lines.len() - 1], "…and 24 more");
+    }
+}
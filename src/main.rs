@@ -1,20 +1,34 @@
+mod cli;
 mod commands;
 mod config;
 mod database;
+mod duplicates;
+mod export;
 mod handlers;
+mod import;
 mod init_tracing;
+mod links;
+mod schedule;
 mod types;
 mod util;
+mod validate;
 
-use std::{env, fs};
+use std::{collections::HashMap, env, fs};
 
-use color_eyre::{Result, eyre::Context as _};
+use clap::Parser;
+use color_eyre::{
+    Result,
+    eyre::{Context as _, eyre},
+};
 use poise::{Framework, FrameworkOptions, builtins::register_in_guild, serenity_prelude::*};
-use tracing::info;
+use tracing::{error, info};
 
-use config::Config;
-use handlers::{error_handler, post_announcements};
-use types::Data;
+use cli::Cli;
+use config::{Config, DataDir};
+use handlers::{error_handler, expire_polls, post_announcements, watch_config};
+use schedule::import_biweekly_flag;
+use types::{Bot, Data};
+use validate::check_configs;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -25,13 +39,35 @@ async fn main() -> Result<()> {
     color_eyre::install()
         .wrap_err("failed to install color_eyre default panic and error report hooks")?;
     init_tracing::init().wrap_err("failed to initialize tracing formatter")?;
-    fs::create_dir_all("./data/").expect("failed to ensure ./data/ exists");
 
+    let cli = Cli::parse();
+    let data_dir = DataDir::new(cli.data_dir);
+    fs::create_dir_all(data_dir.path())
+        .wrap_err_with(|| format!("failed to ensure {} exists", data_dir.path().display()))?;
+
+    match cli.command {
+        None => run(data_dir).await,
+        Some(command) => cli::run(command, &data_dir).await,
+    }
+}
+
+/// Runs the bot.
+async fn run(data_dir: DataDir) -> Result<()> {
     info!("Connecting to the database...");
-    let pool = database::connect().await?;
+    let pool = database::connect(&data_dir).await?;
 
     info!("Loading config...");
-    let config = Config::load()?;
+    let configs = Config::load(&data_dir)?;
+
+    let token = env::var("DISCORD_TOKEN").expect("environment variable DISCORD_TOKEN missing");
+
+    info!("Checking the config...");
+    if let Some(report) = check_configs(Some(&Http::new(&token)), &configs).await {
+        return Err(eyre!(report));
+    }
+
+    // the rows from before multi-guild support belong to the first guild
+    database::adopt_legacy_rows(&pool, configs[0].guild).await?;
 
     let framework = Framework::builder()
         .options(FrameworkOptions {
@@ -44,26 +80,47 @@ async fn main() -> Result<()> {
         })
         .setup(|ctx, _ready, framework| {
             Box::pin(async move {
-                info!("Registering commands...");
-                register_in_guild(ctx, &framework.options().commands, config.guild).await?;
+                let mut guilds = HashMap::new();
+                let reload_on_change = configs[0].reload_on_change;
 
-                info!("Setting up data...");
-                let data = Data::new(pool, config)
-                    .await
-                    .wrap_err("failed to load data")
-                    .unwrap();
+                for (index, config) in configs.into_iter().enumerate() {
+                    let guild = config.guild;
 
-                tokio::spawn(post_announcements(ctx.clone(), data.clone()));
+                    info!("Registering commands in guild {guild}...");
+                    register_in_guild(ctx, &framework.options().commands, guild).await?;
+
+                    info!("Setting up data of guild {guild}...");
+                    let data = Data::new(pool.clone(), config)
+                        .await
+                        .wrap_err("failed to load data")
+                        .unwrap();
+
+                    // like the rows from before multi-guild support, the legacy flag belongs to the first guild
+                    if index == 0
+                        && let Err(e) = import_biweekly_flag(&data, &data_dir).await
+                    {
+                        error!("Failed to import the biweekly flag: {e:#}");
+                    }
+
+                    tokio::spawn(post_announcements(ctx.clone(), data.clone()));
+                    tokio::spawn(expire_polls(ctx.clone(), data.clone()));
+
+                    guilds.insert(guild, data);
+                }
+
+                let bot = Bot { data_dir, guilds };
+
+                if reload_on_change {
+                    tokio::spawn(watch_config(bot.clone()));
+                }
 
                 info!("Done!");
 
-                Ok(data)
+                Ok(bot)
             })
         })
         .build();
 
-    let token = env::var("DISCORD_TOKEN").expect("environment variable DISCORD_TOKEN missing");
-
     let mut client = ClientBuilder::new(token, GatewayIntents::GUILDS)
         .framework(framework)
         .await
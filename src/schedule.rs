@@ -0,0 +1,452 @@
+use chrono::{DateTime, Datelike, Days, NaiveTime, Utc, Weekday};
+use color_eyre::{Result, eyre::Context as _};
+use tokio::fs;
+use tracing::{info, warn};
+
+use crate::{
+    config::{Category, Config, DataDir},
+    types::{Data, Suggestion},
+};
+
+/// Get the next instance of `weekday` at `time` UTC, including today, from `now`.
+fn next_weekday_at(now: DateTime<Utc>, weekday: Weekday, time: NaiveTime) -> DateTime<Utc> {
+    let today_at = now.with_time(time).unwrap();
+
+    if today_at.weekday() == weekday && now < today_at {
+        // If today is the given weekday and it hasn't passed yet, use today
+        today_at
+    } else {
+        // If today is not the given weekday, or it has passed, find the next
+        let mut day_offset = weekday.days_since(today_at.weekday());
+        day_offset += (day_offset == 0) as u32 * 7; // if today is the given weekday, add a week
+        today_at + Days::new(day_offset as u64)
+    }
+}
+
+/// Get the time of the next announcement slot after `now`.
+pub fn next_slot(now: DateTime<Utc>, config: &Config) -> DateTime<Utc> {
+    if config.debug_announcement_period.is_zero() {
+        next_weekday_at(now, config.announcement_weekday, config.announcement_time)
+    } else {
+        now + config.debug_announcement_period
+    }
+}
+
+/// How an announcement slot was handled.
+///
+/// Every recorded slot counts as handled, so neither failed nor skipped slots are retried.
+/// Categories whose announcement failed are still due, so they are announced in the next slot instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotStatus {
+    /// The slot was handled on time.
+    OnTime,
+    /// The slot was missed and posted late.
+    Late,
+    /// The slot was missed and skipped.
+    Skipped,
+    /// At least one announcement of the slot couldn't be posted.
+    Failed,
+}
+
+impl SlotStatus {
+    /// Returns the code stored in the database.
+    pub fn code(self) -> u64 {
+        match self {
+            SlotStatus::OnTime => 0,
+            SlotStatus::Late => 1,
+            SlotStatus::Skipped => 2,
+            SlotStatus::Failed => 3,
+        }
+    }
+}
+
+/// Get the slots after `last_slot` that have passed by `now`.
+///
+/// Slots are only tracked on the weekly schedule, so this is always empty with `debug-announcement-period`.
+pub fn missed_slots(
+    last_slot: DateTime<Utc>,
+    now: DateTime<Utc>,
+    config: &Config,
+) -> Vec<DateTime<Utc>> {
+    if !config.debug_announcement_period.is_zero() {
+        return Vec::new();
+    }
+
+    let mut slots = Vec::new();
+    let mut slot = next_slot(last_slot, config);
+
+    while slot <= now {
+        slots.push(slot);
+        slot = next_slot(slot, config);
+    }
+
+    slots
+}
+
+/// Get the duration between two announcement slots.
+pub fn period(config: &Config) -> chrono::Duration {
+    if config.debug_announcement_period.is_zero() {
+        chrono::Duration::weeks(1)
+    } else {
+        chrono::Duration::from_std(config.debug_announcement_period).unwrap()
+    }
+}
+
+/// Returns whether the slot at `slot` includes an announcement of a category that is announced every `cadence` slots.
+///
+/// An announcement is due if the last one was at least `cadence` slots ago.
+/// Half a slot of tolerance keeps late announcements from shifting the cadence.
+pub fn due(
+    slot: DateTime<Utc>,
+    last_announcement: Option<DateTime<Utc>>,
+    period: chrono::Duration,
+    cadence: u32,
+) -> bool {
+    last_announcement.is_none_or(|last_announcement| {
+        slot - last_announcement >= period * (2 * cadence as i32 - 1) / 2
+    })
+}
+
+/// Projects the times of the next `count` announcements of a category from `now`.
+///
+/// `last_announcement` is the slot of the last announcement of the category,
+/// and `skip_next` is whether the next pick of the category is skipped.
+pub fn project_announcements(
+    now: DateTime<Utc>,
+    config: &Config,
+    category: &Category,
+    mut last_announcement: Option<DateTime<Utc>>,
+    skip_next: bool,
+    count: usize,
+) -> Vec<DateTime<Utc>> {
+    let first = next_slot(now, config);
+    let period = period(config);
+
+    (0..)
+        .map(|week| first + period * week)
+        .filter(|slot| {
+            let due = due(*slot, last_announcement, period, category.cadence);
+
+            if due {
+                last_announcement = Some(*slot);
+            }
+
+            due
+        })
+        .skip(skip_next as usize)
+        .take(count)
+        .collect()
+}
+
+/// What happens to a category in an announcement slot.
+pub enum Announcement {
+    /// The category is not due in the slot.
+    NotDue,
+    /// A facilitator skipped the next pick of the category.
+    Skipped,
+    /// The queue of the category is empty.
+    EmptyQueue,
+    /// The next suggestion in the queue is announced.
+    Post(Suggestion),
+}
+
+/// Decides what happens to every category of a guild in a slot, without changing anything.
+///
+/// The bot carries out this plan, and `announce` prints it, so that both make the same decisions.
+pub async fn plan_slot<'a>(
+    data: &Data,
+    config: &'a Config,
+    slot: DateTime<Utc>,
+) -> Vec<(&'a Category, Result<Announcement>)> {
+    let period = period(config);
+    let mut plan = Vec::new();
+
+    for category in &config.categories {
+        plan.push((
+            category,
+            plan_announcement(data, category, slot, period).await,
+        ));
+    }
+
+    plan
+}
+
+/// Decides what happens to a category in a slot.
+async fn plan_announcement(
+    data: &Data,
+    category: &Category,
+    slot: DateTime<Utc>,
+    period: chrono::Duration,
+) -> Result<Announcement> {
+    let last_announcement = data
+        .fetch_last_announcement(&category.id)
+        .await
+        .wrap_err("failed to fetch the last announcement")?;
+
+    if !due(slot, last_announcement, period, category.cadence) {
+        return Ok(Announcement::NotDue);
+    }
+
+    if data.fetch_queue_skip(&category.id).await? {
+        return Ok(Announcement::Skipped);
+    }
+
+    Ok(match data.pick_suggestion(&category.id).await? {
+        Some(suggestion) => Announcement::Post(suggestion),
+        None => Announcement::EmptyQueue,
+    })
+}
+
+/// Imports the internal cadence from `biweekly_flag.bin`, which was used before it was stored in the database.
+///
+/// The file is removed once it has been imported. It is kept if the legacy internal category is not configured.
+pub async fn import_biweekly_flag(data: &Data, data_dir: &DataDir) -> Result<()> {
+    let path = data_dir.biweekly_flag();
+
+    if !fs::try_exists(&path)
+        .await
+        .wrap_err_with(|| format!("failed to check if {} exists", path.display()))?
+    {
+        return Ok(());
+    }
+
+    let config = data.config();
+
+    let Some(category) = config.legacy_internal_category() else {
+        warn!(
+            "Not importing {}, because there is no `internal` category to import it into. Remove the file if the category was dropped.",
+            path.display()
+        );
+        return Ok(());
+    };
+
+    let biweekly_flag = fs::read(&path)
+        .await
+        .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+
+    // the flag was whether the next slot includes an internal announcement, and an empty file meant it does
+    let next_includes_internal = biweekly_flag.first().is_none_or(|byte| byte % 2 == 0);
+
+    if !next_includes_internal && data.fetch_last_announcement(&category.id).await?.is_none() {
+        let previous_slot = next_slot(Utc::now(), &config) - period(&config);
+        data.record_announcement(&category.id, previous_slot)
+            .await?;
+    }
+
+    fs::remove_file(&path)
+        .await
+        .wrap_err_with(|| format!("failed to remove {}", path.display()))?;
+
+    info!(
+        "Imported the internal announcement cadence from {}",
+        path.display()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, time::Duration};
+
+    use chrono::NaiveDateTime;
+    use figment::{
+        Figment,
+        providers::{Format, Toml},
+    };
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+        guild-id = 1
+        form-timeout = 600
+        announcement-weekday = "monday"
+        announcement-time = "12:00"
+        announcement-role-id = 6
+        poll-threshold = 3
+        facilitator-role-id = 7
+
+        [[categories]]
+        id = "external"
+        name = "External"
+        poll-title = "External Poll"
+        announcement-title = "External Announcement"
+        poll-channel-id = 5
+        announcement-channel-id = 4
+
+        [[categories]]
+        id = "internal"
+        name = "Internal"
+        poll-title = "Internal Poll"
+        announcement-title = "Internal Announcement"
+        poll-channel-id = 3
+        announcement-channel-id = 2
+        cadence = 2
+    "#;
+
+    fn config() -> Config {
+        Figment::from(Toml::string(CONFIG)).extract().unwrap()
+    }
+
+    fn parse_date(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_next_weekday_at() {
+        const TESTS: &[(&str, &str, &str)] = &[
+            ("2025-01-01 00:00:00", "mon 00:00:00", "2025-01-06 00:00:00"),
+            ("2025-01-02 00:00:00", "mon 00:00:00", "2025-01-06 00:00:00"),
+            ("2025-01-01 11:59:59", "wed 12:00:00", "2025-01-01 12:00:00"),
+            ("2025-01-01 12:00:00", "wed 12:00:00", "2025-01-08 12:00:00"),
+        ];
+
+        for (now, weekday_time, expected) in TESTS {
+            let now = parse_date(now).and_utc();
+            let weekday = Weekday::from_str(&weekday_time[..3]).unwrap();
+            let time = NaiveTime::parse_from_str(&weekday_time[4..], "%H:%M:%S").unwrap();
+            let actual = next_weekday_at(now, weekday, time);
+            let expected = parse_date(expected).and_utc();
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_missed_slots() {
+        const TESTS: &[(&str, &str, &[&str])] = &[
+            ("2025-01-06 12:00:00", "2025-01-13 11:59:59", &[]),
+            (
+                "2025-01-06 12:00:00",
+                "2025-01-13 12:00:00",
+                &["2025-01-13 12:00:00"],
+            ),
+            (
+                "2025-01-06 12:00:00",
+                "2025-01-29 00:00:00",
+                &[
+                    "2025-01-13 12:00:00",
+                    "2025-01-20 12:00:00",
+                    "2025-01-27 12:00:00",
+                ],
+            ),
+        ];
+
+        let config = config();
+        // slots aren't tracked with a debug period
+        let mut debug_config = config.clone();
+        debug_config.debug_announcement_period = Duration::from_secs(60);
+
+        for (last_slot, now, expected) in TESTS {
+            let last_slot = parse_date(last_slot).and_utc();
+            let now = parse_date(now).and_utc();
+            let expected = expected
+                .iter()
+                .map(|date| parse_date(date).and_utc())
+                .collect::<Vec<_>>();
+
+            assert_eq!(missed_slots(last_slot, now, &config), expected);
+            assert!(missed_slots(last_slot, now, &debug_config).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_project_announcements() {
+        const TESTS: &[(&str, Option<&str>, bool, &[&str])] = &[
+            (
+                "external",
+                None,
+                false,
+                &[
+                    "2025-01-06 12:00:00",
+                    "2025-01-13 12:00:00",
+                    "2025-01-20 12:00:00",
+                ],
+            ),
+            (
+                "external",
+                None,
+                true,
+                &["2025-01-13 12:00:00", "2025-01-20 12:00:00"],
+            ),
+            (
+                "internal",
+                None,
+                false,
+                &[
+                    "2025-01-06 12:00:00",
+                    "2025-01-20 12:00:00",
+                    "2025-02-03 12:00:00",
+                ],
+            ),
+            (
+                "internal",
+                Some("2024-12-30 12:00:00"),
+                false,
+                &["2025-01-13 12:00:00", "2025-01-27 12:00:00"],
+            ),
+            // skipping a biweekly pick delays it by two weeks
+            (
+                "internal",
+                None,
+                true,
+                &["2025-01-20 12:00:00", "2025-02-03 12:00:00"],
+            ),
+        ];
+
+        let config = config();
+        let now = parse_date("2025-01-01 00:00:00").and_utc();
+
+        for (category, last_announcement, skip_next, expected) in TESTS {
+            let category = config.category(category).unwrap();
+            let last_announcement = last_announcement.map(|date| parse_date(date).and_utc());
+            let expected = expected
+                .iter()
+                .map(|date| parse_date(date).and_utc())
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                project_announcements(
+                    now,
+                    &config,
+                    category,
+                    last_announcement,
+                    *skip_next,
+                    expected.len()
+                ),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_due() {
+        const TESTS: &[(&str, Option<&str>, u32, bool)] = &[
+            ("2025-01-06 12:00:00", None, 2, true),
+            ("2025-01-06 12:00:00", Some("2024-12-30 12:00:00"), 1, true),
+            ("2025-01-06 12:00:00", Some("2024-12-30 12:00:00"), 2, false),
+            ("2025-01-06 12:00:00", Some("2024-12-23 12:00:00"), 2, true),
+            ("2025-01-06 12:00:00", Some("2024-12-23 12:00:00"), 3, false),
+            // a failed or skipped announcement is retried in the next slot
+            ("2025-01-06 12:00:00", Some("2024-12-16 12:00:00"), 2, true),
+            // a late announcement doesn't shift the cadence
+            ("2025-01-06 12:00:00", Some("2024-12-23 15:00:00"), 2, true),
+            ("2025-01-06 12:00:00", Some("2025-01-01 09:00:00"), 2, false),
+        ];
+
+        for (slot, last_announcement, cadence, expected) in TESTS {
+            let slot = parse_date(slot).and_utc();
+            let last_announcement = last_announcement.map(|date| parse_date(date).and_utc());
+
+            assert_eq!(
+                due(
+                    slot,
+                    last_announcement,
+                    chrono::Duration::weeks(1),
+                    *cadence
+                ),
+                *expected
+            );
+        }
+    }
+}
@@ -1,59 +1,158 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
+use chrono::{DateTime, NaiveDateTime, Utc};
 use color_eyre::{
     Result,
     eyre::{Context as _, eyre},
 };
+use itertools::Itertools;
 use poise::serenity_prelude::*;
 use sqlx::sqlite::SqlitePool;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, watch};
+use tracing::{error, info};
 
 use crate::{
-    config::Config,
+    config::{self, Category, Config, DataDir, PollRule},
     database,
-    util::{artist_capital, get_icon_url},
+    duplicates::{self, Candidate, Duplicate},
+    export::{Export, ExportFilter},
+    links::{Link, format_links, join_links, parse_links},
+    schedule::SlotStatus,
+    util::get_icon_url,
 };
 
+/// The maximum length of the artist and album names of a suggestion.
+const MAX_NAME_LENGTH: usize = 256;
+
+/// The maximum length of the links and notes of a suggestion.
+const MAX_TEXT_LENGTH: usize = 1024;
+
+/// The framework data, holding the data of every configured guild.
+#[derive(Clone)]
+pub struct Bot {
+    pub data_dir: DataDir,
+    pub guilds: HashMap<GuildId, Data>,
+}
+
+impl Bot {
+    /// Returns the data of the guild an interaction came from.
+    pub fn guild(&self, guild: Option<GuildId>) -> Result<&Data> {
+        guild
+            .and_then(|guild| self.guilds.get(&guild))
+            .ok_or_else(|| eyre!("the interaction did not come from a configured guild"))
+    }
+
+    /// Reloads the config of one guild, or of every guild if `guild` is `None`, and returns what changed.
+    ///
+    /// Nothing is swapped in unless the configs of all reloaded guilds are valid.
+    /// The changes of the other guilds are not reported, since they may be private.
+    pub fn reload_config(&self, guild: Option<GuildId>) -> Result<Vec<String>> {
+        let configs = Config::load(&self.data_dir)?;
+
+        if configs.len() != self.guilds.len()
+            || configs
+                .iter()
+                .any(|config| !self.guilds.contains_key(&config.guild))
+        {
+            return Err(eyre!("adding or removing guilds requires a restart"));
+        }
+
+        let configs = configs
+            .into_iter()
+            .filter(|config| guild.is_none_or(|guild| config.guild == guild))
+            .collect::<Vec<_>>();
+
+        for config in &configs {
+            let problems = config.problems();
+
+            if !problems.is_empty() {
+                return Err(eyre!(
+                    "invalid config for guild {}: {}",
+                    config.guild,
+                    problems.join(", ")
+                ));
+            }
+        }
+
+        let mut changed = Vec::new();
+        let mut changes = Vec::new();
+
+        for config in configs {
+            let guild_changes = config::diff(&self.guilds[&config.guild].config(), &config)?;
+
+            if !guild_changes.is_empty() {
+                changes.extend(
+                    guild_changes
+                        .into_iter()
+                        .map(|change| format!("guild {}: {change}", config.guild)),
+                );
+                changed.push(config);
+            }
+        }
+
+        // only changed configs are swapped, so the announcement loops of other guilds keep waiting
+        for config in changed {
+            self.guilds[&config.guild]
+                .config
+                .send_replace(Arc::new(config));
+        }
+
+        if changes.is_empty() {
+            info!("Reloaded the config without changes");
+        } else {
+            info!("Reloaded the config:\n{}", changes.join("\n"));
+        }
+
+        Ok(changes)
+    }
+}
+
+/// The data of a single guild.
 #[derive(Clone)]
 pub struct Data {
     pub pool: SqlitePool,
-    pub config: Config,
-    pub polls: Arc<Mutex<Vec<Poll>>>, // this is here to avoid data races
-                                      // suggestions are not used this way because they are not modified frequently
+    pub guild: GuildId,
+    config: Arc<watch::Sender<Arc<Config>>>, // swapped when the config is reloaded
+    pub polls: Arc<Mutex<Vec<Poll>>>,        // this is here to avoid data races
+                                             // suggestions are not used this way because they are not modified frequently
 }
 
 impl Data {
     pub async fn new(pool: SqlitePool, config: Config) -> Result<Data> {
-        let polls = database::fetch_polls(&pool).await?;
+        let polls = database::fetch_polls(&pool, config.guild).await?;
 
         Ok(Data {
             pool,
-            config,
+            guild: config.guild,
+            config: Arc::new(watch::Sender::new(Arc::new(config))),
             polls: Arc::new(Mutex::new(polls)),
         })
     }
 
-    /// Returns the channel to post announcements to.
-    pub fn get_announcement_channel(&self, internal: bool) -> ChannelId {
-        if internal {
-            self.config.internal_channel
-        } else {
-            self.config.external_channel
-        }
+    /// Returns the current config.
+    ///
+    /// The config may be reloaded at any time, so long tasks should call this again instead of keeping it.
+    pub fn config(&self) -> Arc<Config> {
+        self.config.borrow().clone()
     }
 
-    /// Returns the channel to post suggestion polls to.
-    pub fn get_poll_channel(&self, internal: bool) -> ChannelId {
-        if internal {
-            self.config.internal_poll_channel
-        } else {
-            self.config.external_poll_channel
-        }
+    /// Returns a receiver that is notified whenever the config is reloaded.
+    pub fn subscribe_config(&self) -> watch::Receiver<Arc<Config>> {
+        self.config.subscribe()
     }
 
     /// Inserts a new suggestion into the database.
     pub async fn insert_suggestion(&self, suggestion: &Suggestion, poll_id: u64) -> Result<()> {
-        database::insert_suggestion(&self.pool, suggestion, poll_id).await
+        database::insert_suggestion(&self.pool, self.guild, suggestion, poll_id).await
+    }
+
+    /// Inserts an approved suggestion without a poll at the back of its queue and returns its ID.
+    pub async fn insert_queued_suggestion(&self, suggestion: &Suggestion) -> Result<u64> {
+        database::insert_queued_suggestion(&self.pool, self.guild, suggestion).await
     }
 
     /// Fetches a suggestion by its poll ID.
@@ -63,6 +162,53 @@ impl Data {
             .wrap_err("failed to fetch suggestion")
     }
 
+    /// Finds existing or past suggestions that look like duplicates of a suggestion.
+    ///
+    /// A stored suggestion is not compared against itself.
+    pub async fn find_duplicates(&self, suggestion: &Suggestion) -> Result<Vec<Duplicate>> {
+        let candidates =
+            database::fetch_duplicate_candidates(&self.pool, self.guild, suggestion.id)
+                .await
+                .wrap_err("failed to fetch duplicate candidates")?;
+
+        Ok(duplicates::find(suggestion, &candidates))
+    }
+
+    /// Fetches all suggestions and archived suggestions to compare many suggestions against.
+    pub async fn fetch_duplicate_candidates(&self) -> Result<Vec<Candidate>> {
+        database::fetch_duplicate_candidates(&self.pool, self.guild, 0)
+            .await
+            .wrap_err("failed to fetch duplicate candidates")
+    }
+
+    /// Formats duplicates one per line, linking the polls in the poll channels of their categories.
+    pub fn format_duplicates(&self, duplicates: &[Duplicate]) -> String {
+        let config = self.config();
+
+        duplicates
+            .iter()
+            .map(|duplicate| {
+                duplicate.format(self.guild, |category| {
+                    config
+                        .category(category)
+                        .ok()
+                        .map(|category| category.poll_channel)
+                })
+            })
+            .join("\n")
+    }
+
+    /// Updates the fields of the suggestion with the given ID.
+    pub async fn update_suggestion(
+        &self,
+        suggestion_id: u64,
+        suggestion: &Suggestion,
+    ) -> Result<()> {
+        database::update_suggestion(&self.pool, suggestion_id, suggestion)
+            .await
+            .wrap_err("failed to update suggestion")
+    }
+
     /// Approves the suggestion with the given poll ID.
     pub async fn approve_suggestion(&self, poll_id: u64) -> Result<()> {
         database::approve_suggestion(&self.pool, poll_id)
@@ -70,16 +216,299 @@ impl Data {
             .wrap_err("failed to approve suggestion")
     }
 
-    /// Fetches the oldest approved suggestion but does not remove it from the database.
-    async fn pick_suggestion(&self, internal: bool) -> Result<Suggestion> {
-        database::pick_suggestion(&self.pool, internal)
+    /// Fetches the approved suggestions in the order they will be announced.
+    pub async fn fetch_queue(&self, category: &str) -> Result<Vec<QueueEntry>> {
+        database::fetch_queue(&self.pool, self.guild, category)
+            .await
+            .wrap_err("failed to fetch queue")
+    }
+
+    /// Moves an approved suggestion to a position in its queue, or to the back if `position` is `None`.
+    ///
+    /// Returns the new position, or `None` if the suggestion isn't queued.
+    pub async fn move_in_queue(
+        &self,
+        suggestion_id: u64,
+        position: Option<usize>,
+    ) -> Result<Option<usize>> {
+        database::move_in_queue(&self.pool, self.guild, suggestion_id, position)
+            .await
+            .wrap_err("failed to move suggestion in queue")
+    }
+
+    /// Sets whether the next scheduled pick of a category is skipped.
+    pub async fn set_queue_skip(&self, category: &str, skip: bool) -> Result<()> {
+        database::set_queue_skip(&self.pool, self.guild, category, skip).await
+    }
+
+    /// Returns whether the next scheduled pick of a category is skipped.
+    pub async fn fetch_queue_skip(&self, category: &str) -> Result<bool> {
+        database::fetch_queue_skip(&self.pool, self.guild, category).await
+    }
+
+    /// Removes the skip of the next scheduled pick of a category and returns whether there was one.
+    pub async fn take_queue_skip(&self, category: &str) -> Result<bool> {
+        database::take_queue_skip(&self.pool, self.guild, category).await
+    }
+
+    /// Fetches the next approved suggestion in the queue, if there is one, but does not remove it from the database.
+    pub async fn pick_suggestion(&self, category: &str) -> Result<Option<Suggestion>> {
+        database::pick_suggestion(&self.pool, self.guild, category)
             .await
             .wrap_err("failed to pick suggestion")
     }
 
     /// Removes the suggestion with the given ID and the associated poll from the database (and not from the cache).
-    pub async fn remove_suggestion_and_poll(&self, suggestion_id: u64) -> Result<()> {
-        database::remove_suggestion_and_poll(&self.pool, suggestion_id).await
+    ///
+    /// The suggestion is archived with the given veto `reason`. Returns its ID in the archive.
+    pub async fn remove_suggestion_and_poll(
+        &self,
+        suggestion_id: u64,
+        reason: Option<&str>,
+    ) -> Result<u64> {
+        database::remove_suggestion_and_poll(&self.pool, suggestion_id, reason).await
+    }
+
+    /// Marks a poll as expired and archives its suggestion, which is returned.
+    pub async fn expire_poll(&self, poll_id: u64) -> Result<Suggestion> {
+        let suggestion = self.fetch_suggestion(poll_id).await?;
+
+        database::expire_poll(&self.pool, poll_id)
+            .await
+            .wrap_err("failed to expire poll")?;
+
+        Ok(suggestion)
+    }
+
+    /// Counts the suggestions of every category and the other rows of the guild.
+    pub async fn fetch_stats(&self) -> Result<Stats> {
+        database::fetch_stats(&self.pool, self.guild).await
+    }
+
+    /// Exports the suggestions, polls and archived suggestions that match a filter.
+    pub async fn export(&self, filter: &ExportFilter) -> Result<Export> {
+        let mut export = database::fetch_export(&self.pool, self.guild, filter).await?;
+        // the status is only known once the rows are decoded
+        export.filter(filter);
+
+        Ok(export)
+    }
+
+    /// Fetches the slot of the last announcement of a category.
+    pub async fn fetch_last_announcement(&self, category: &str) -> Result<Option<DateTime<Utc>>> {
+        Ok(
+            database::fetch_last_announcement(&self.pool, self.guild, category)
+                .await?
+                .map(|timestamp| timestamp.and_utc()),
+        )
+    }
+
+    /// Records an announcement of a category in the given slot.
+    pub async fn record_announcement(&self, category: &str, slot: DateTime<Utc>) -> Result<()> {
+        database::set_last_announcement(&self.pool, self.guild, category, slot.naive_utc()).await
+    }
+
+    /// Fetches the latest handled announcement slot.
+    pub async fn fetch_last_slot(&self) -> Result<Option<DateTime<Utc>>> {
+        Ok(database::fetch_last_slot(&self.pool, self.guild)
+            .await?
+            .map(|slot| slot.and_utc()))
+    }
+
+    /// Records how an announcement slot was handled.
+    pub async fn record_slot(&self, slot: DateTime<Utc>, status: SlotStatus) -> Result<()> {
+        database::insert_slot(&self.pool, self.guild, slot.naive_utc(), status).await
+    }
+
+    /// Checks whether a user may submit a suggestion in a category.
+    ///
+    /// Returns why they may not, if they may not.
+    pub async fn check_submission_limits(
+        &self,
+        user_id: UserId,
+        category: &Category,
+    ) -> Result<Option<String>> {
+        let config = self.config();
+        let limit = config.suggestion_limit;
+
+        if limit != 0
+            && database::count_suggestions(&self.pool, self.guild, user_id, &category.id).await?
+                >= limit
+        {
+            return Ok(Some(format!(
+                "You already have {limit} {} suggestion(s) waiting for a poll or an announcement. You can suggest again once one of them is featured, revoked, vetoed or expired.",
+                category.name
+            )));
+        }
+
+        let cooldown = config.suggestion_cooldown;
+
+        if !cooldown.is_zero()
+            && let Some(last_submission) =
+                database::fetch_last_submission(&self.pool, self.guild, user_id).await?
+        {
+            let next_submission = last_submission.and_utc() + cooldown;
+
+            if next_submission > Utc::now() {
+                return Ok(Some(format!(
+                    "You're suggesting too quickly! You can suggest again <t:{}:R>.",
+                    next_submission.timestamp()
+                )));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Blocks a user from submitting suggestions. Returns `false` if they were already blocked.
+    pub async fn block_user(&self, user_id: UserId, reason: Option<&str>) -> Result<bool> {
+        database::block_user(&self.pool, self.guild, user_id, reason).await
+    }
+
+    /// Unblocks a user. Returns `false` if they weren't blocked.
+    pub async fn unblock_user(&self, user_id: UserId) -> Result<bool> {
+        database::unblock_user(&self.pool, self.guild, user_id).await
+    }
+
+    /// Checks whether a user is blocked.
+    pub async fn is_user_blocked(&self, user_id: UserId) -> Result<bool> {
+        database::is_user_blocked(&self.pool, self.guild, user_id).await
+    }
+
+    /// Fetches the blocked users, most recently blocked first.
+    pub async fn fetch_blocked_users(&self) -> Result<Vec<BlockedUser>> {
+        database::fetch_blocked_users(&self.pool, self.guild).await
+    }
+
+    /// Blocks an artist from being suggested. Returns `false` if they were already blocked.
+    pub async fn block_artist(&self, artist_name: &str, reason: Option<&str>) -> Result<bool> {
+        database::block_artist(&self.pool, self.guild, artist_name, reason).await
+    }
+
+    /// Unblocks an artist. Returns `false` if they weren't blocked.
+    pub async fn unblock_artist(&self, artist_name: &str) -> Result<bool> {
+        database::unblock_artist(&self.pool, self.guild, artist_name).await
+    }
+
+    /// Checks whether an artist is blocked, comparing normalized names.
+    pub async fn is_artist_blocked(&self, artist_name: &str) -> Result<bool> {
+        database::is_artist_blocked(&self.pool, self.guild, artist_name).await
+    }
+
+    /// Fetches the blocked artists, most recently blocked first.
+    pub async fn fetch_blocked_artists(&self) -> Result<Vec<BlockedArtist>> {
+        database::fetch_blocked_artists(&self.pool, self.guild).await
+    }
+
+    /// Records a submission rejected because of the blocklist.
+    pub async fn record_blocked_attempt(&self, attempt: &BlockedAttempt) -> Result<()> {
+        info!(
+            "Rejected a blocked {} suggestion by {}",
+            attempt.category, attempt.user_id
+        );
+        database::insert_blocked_attempt(&self.pool, self.guild, attempt).await
+    }
+
+    /// Fetches the recorded blocked attempts, most recent first.
+    pub async fn fetch_blocked_attempts(&self) -> Result<Vec<RecordedBlockedAttempt>> {
+        database::fetch_blocked_attempts(&self.pool, self.guild).await
+    }
+
+    /// Fetches an archived suggestion by its ID.
+    pub async fn fetch_archived_suggestion(
+        &self,
+        archived_id: u64,
+    ) -> Result<Option<ArchivedSuggestion>> {
+        database::fetch_archived_suggestion(&self.pool, self.guild, archived_id).await
+    }
+
+    /// Searches the archived suggestions, most recently submitted first.
+    pub async fn search_history(&self, filter: &HistoryFilter) -> Result<Vec<ArchivedSuggestion>> {
+        database::search_history(&self.pool, self.guild, filter).await
+    }
+
+    /// Moves an archived suggestion back into the approved queue and returns its new ID.
+    pub async fn restore_to_queue(&self, archived_id: u64) -> Result<u64> {
+        database::restore_suggestion(&self.pool, self.guild, archived_id, None, true).await
+    }
+
+    /// Moves an archived suggestion back into `suggestions` with a fresh poll and returns the new poll's ID.
+    pub async fn restore_to_poll(
+        &self,
+        ctx: &Context,
+        archived: &ArchivedSuggestion,
+    ) -> Result<u64> {
+        let suggestion = &archived.suggestion;
+        let poll_id = self
+            .create_poll(ctx, suggestion, true)
+            .await
+            .wrap_err("failed to create poll")?;
+
+        if let Err(e) = database::restore_suggestion(
+            &self.pool,
+            self.guild,
+            suggestion.id,
+            Some(poll_id),
+            false,
+        )
+        .await
+        .wrap_err("failed to restore suggestion")
+        {
+            if let Err(e) = self.delete_poll(ctx, poll_id, &suggestion.category).await {
+                error!("Failed to delete the poll of a suggestion that wasn't restored: {e:#}");
+            }
+
+            return Err(e);
+        }
+
+        Ok(poll_id)
+    }
+
+    /// Sends a direct message to a user.
+    ///
+    /// If their direct messages are closed, the message is stored as a notice for their next `/suggest`.
+    pub async fn notify(
+        &self,
+        cache_http: impl CacheHttp,
+        user_id: UserId,
+        content: String,
+    ) -> Result<()> {
+        if let Err(e) = user_id
+            .direct_message(cache_http, CreateMessage::new().content(&content))
+            .await
+        {
+            info!("Failed to send a direct message to {user_id}, storing a notice instead: {e:#}");
+            database::insert_notice(&self.pool, self.guild, user_id, &content).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends a message to the facilitator channel, or only logs it if there is none.
+    pub async fn notify_facilitators(
+        &self,
+        cache_http: impl CacheHttp,
+        content: String,
+    ) -> Result<()> {
+        info!("Facilitator notification: {content}");
+
+        let Some(channel) = self.config().facilitator_channel else {
+            return Ok(());
+        };
+
+        channel
+            .send_message(cache_http, CreateMessage::new().content(content))
+            .await
+            .wrap_err("failed to send message")?;
+
+        Ok(())
+    }
+
+    /// Removes and returns the stored notices for a user.
+    pub async fn take_notices(&self, user_id: UserId) -> Result<Vec<String>> {
+        database::take_notices(&self.pool, self.guild, user_id)
+            .await
+            .wrap_err("failed to take notices")
     }
 
     /// Inserts a new poll to the database and returns its ID.
@@ -87,11 +516,12 @@ impl Data {
         &self,
         message_id: MessageId,
         author_id: UserId,
-        internal: bool,
+        category: &str,
     ) -> Result<u64> {
-        let poll_id = database::insert_poll(&self.pool, message_id, author_id, internal).await?;
+        let poll_id =
+            database::insert_poll(&self.pool, self.guild, message_id, author_id, category).await?;
 
-        let poll = Poll::new(poll_id, message_id, author_id, internal);
+        let poll = Poll::new(poll_id, message_id, author_id, category.to_string());
         self.polls.lock().await.push(poll);
 
         Ok(poll_id)
@@ -107,9 +537,11 @@ impl Data {
         cache_http: impl CacheHttp,
         suggestion: &Suggestion,
         status: &PollStatus,
-    ) -> CreateEmbed {
-        let (status, color) = status.format(self.config.poll_threshold);
-        let icon_url = get_icon_url(&cache_http, self.config.guild, suggestion.user_id).await;
+    ) -> Result<CreateEmbed> {
+        let config = self.config();
+        let category = config.category(&suggestion.category)?;
+        let (status, color) = status.format(&config, category);
+        let icon_url = get_icon_url(&cache_http, self.guild, suggestion.user_id).await;
 
         let embed_author = CreateEmbedAuthor::new(suggestion.username.clone())
             .url(format!(
@@ -118,15 +550,10 @@ impl Data {
             ))
             .icon_url(icon_url);
 
-        let embed_title = format!(
-            "{} Feature Artist Submission",
-            artist_capital(suggestion.internal)
-        );
-
         let mut embed_fields = vec![
             ("Artist Name", suggestion.artist_name.clone(), true),
             ("Album Name", suggestion.album_name.clone(), true),
-            ("Album Link(s)", suggestion.links.clone(), false),
+            ("Album Link(s)", format_links(&suggestion.links), false),
         ];
 
         if let Some(notes) = &suggestion.notes {
@@ -135,27 +562,30 @@ impl Data {
 
         embed_fields.push(("Status", status, false));
 
-        CreateEmbed::new()
+        Ok(CreateEmbed::new()
             .author(embed_author)
-            .title(embed_title)
+            .title(&category.poll_title)
             // .description("Users may upvote this submission with 👍")
             .fields(embed_fields)
-            .color(color)
+            .color(color))
     }
 
     /// Creates a new poll for a suggestion and returns its ID.
-    pub async fn create_poll(&self, ctx: &Context, suggestion: &Suggestion) -> Result<u64> {
+    ///
+    /// The submitter is named in the message, but only pinged if `mention` is set.
+    pub async fn create_poll(
+        &self,
+        ctx: &Context,
+        suggestion: &Suggestion,
+        mention: bool,
+    ) -> Result<u64> {
         let embed = self
             .build_poll_embed(&ctx, suggestion, &PollStatus::default())
-            .await;
+            .await?;
 
-        let components = vec![CreateActionRow::Buttons(vec![
-            CreateButton::new("poll:upvote").label("Upvote").emoji('👍'),
-            CreateButton::new("poll:revoke").label("Revoke").emoji('🗑'),
-            CreateButton::new("poll:veto").label("Veto").emoji('🛑'),
-        ])];
+        let components = PollStatus::default().components();
 
-        let message_builder = CreateMessage::new()
+        let mut message_builder = CreateMessage::new()
             .content(format!(
                 "<@{}> here's your new submission!",
                 suggestion.user_id
@@ -163,30 +593,87 @@ impl Data {
             .add_embed(embed)
             .components(components);
 
+        if !mention {
+            message_builder = message_builder.allowed_mentions(CreateAllowedMentions::new());
+        }
+
         // send the poll
         let message = self
-            .get_poll_channel(suggestion.internal)
+            .config()
+            .category(&suggestion.category)?
+            .poll_channel
             .send_message(ctx, message_builder)
             .await
             .wrap_err("failed to send message")?;
 
         // add the poll
         let poll_id = self
-            .insert_poll(message.id, suggestion.user_id, suggestion.internal)
+            .insert_poll(message.id, suggestion.user_id, &suggestion.category)
             .await?;
 
         Ok(poll_id)
     }
 
-    /// Fetches and removes the oldest suggestion from the database and posts it to the appropriate channel.
+    /// Creates a poll for a new suggestion, inserts the suggestion and returns the poll's ID.
+    ///
+    /// If the suggestion can't be inserted, the poll is deleted again so that it isn't left without a suggestion.
+    pub async fn open_poll(
+        &self,
+        ctx: &Context,
+        suggestion: &Suggestion,
+        mention: bool,
+    ) -> Result<u64> {
+        let poll_id = self
+            .create_poll(ctx, suggestion, mention)
+            .await
+            .wrap_err("failed to create poll")?;
+
+        if let Err(e) = self
+            .insert_suggestion(suggestion, poll_id)
+            .await
+            .wrap_err("failed to insert suggestion")
+        {
+            if let Err(e) = self.delete_poll(ctx, poll_id, &suggestion.category).await {
+                error!("Failed to delete the poll of a suggestion that wasn't inserted: {e:#}");
+            }
+
+            return Err(e);
+        }
+
+        Ok(poll_id)
+    }
+
+    /// Deletes a poll without a suggestion and its message.
+    async fn delete_poll(&self, ctx: &Context, poll_id: u64, category: &str) -> Result<()> {
+        let poll = {
+            let mut polls = self.polls.lock().await;
+            let index = polls
+                .iter()
+                .position(|poll| poll.id == poll_id)
+                .ok_or(eyre!("poll {poll_id} not found"))?;
+
+            polls.remove(index)
+        };
+
+        database::delete_poll(&self.pool, poll_id).await?;
+
+        self.config()
+            .category(category)?
+            .poll_channel
+            .delete_message(ctx, poll.message_id)
+            .await
+            .wrap_err("failed to delete message")
+    }
+
+    /// Posts the announcement of a suggestion picked from the queue of a category and archives the suggestion.
     pub async fn post_announcement(
         &self,
         cache_http: impl CacheHttp,
-        internal: bool,
+        config: &Config,
+        category: &Category,
+        suggestion: &Suggestion,
     ) -> Result<()> {
-        let suggestion = self.pick_suggestion(internal).await?;
-
-        let icon_url = get_icon_url(&cache_http, self.config.guild, suggestion.user_id).await;
+        let icon_url = get_icon_url(&cache_http, self.guild, suggestion.user_id).await;
 
         let embed_author = CreateEmbedAuthor::new(suggestion.username.clone())
             .url(format!(
@@ -195,38 +682,25 @@ impl Data {
             ))
             .icon_url(icon_url);
 
-        let embed_title = format!(
-            "New {} Feature Artist! 🌟 🎵",
-            artist_capital(suggestion.internal)
-        );
-
-        let mut embed_fields = vec![
-            ("Artist Name", suggestion.artist_name.clone(), true),
-            ("Album Name", suggestion.album_name.clone(), true),
-            ("Album Link(s)", suggestion.links.clone(), false),
-        ];
-
-        if let Some(notes) = &suggestion.notes {
-            embed_fields.push(("Other Comments", notes.clone(), false));
-        }
-
         let embed = CreateEmbed::new()
             .author(embed_author)
-            .title(embed_title)
-            .fields(embed_fields)
+            .title(&category.announcement_title)
+            .fields(suggestion.announcement_fields())
             .color((87, 242, 135));
 
-        self.get_announcement_channel(internal)
-            .send_message(
-                cache_http,
-                CreateMessage::new()
-                    .content(format!("<@&{}>", self.config.announcement_role))
-                    .embed(embed),
-            )
+        let mut message = CreateMessage::new().embed(embed);
+
+        if let Some(ping_role) = config.ping_role(category) {
+            message = message.content(format!("<@&{ping_role}>"));
+        }
+
+        category
+            .announcement_channel
+            .send_message(cache_http, message)
             .await
             .wrap_err("failed to send message")?;
 
-        self.remove_suggestion_and_poll(suggestion.id).await?;
+        self.remove_suggestion_and_poll(suggestion.id, None).await?;
 
         Ok(())
     }
@@ -238,95 +712,486 @@ pub struct Suggestion {
     pub username: String,
     pub artist_name: String,
     pub album_name: String,
-    pub links: String,
+    pub links: Vec<Link>,
     pub notes: Option<String>,
-    pub internal: bool,
+    pub category: String,
 }
 
 impl Suggestion {
-    pub fn parse_response(response: &QuickModalResponse, internal: bool) -> Result<Suggestion> {
+    /// Returns the fields of the announcement embed as `(name, value, inline)`.
+    pub fn announcement_fields(&self) -> Vec<(&'static str, String, bool)> {
+        let mut fields = vec![
+            ("Artist Name", self.artist_name.clone(), true),
+            ("Album Name", self.album_name.clone(), true),
+            ("Album Link(s)", format_links(&self.links), false),
+        ];
+
+        if let Some(notes) = &self.notes {
+            fields.push(("Other Comments", notes.clone(), false));
+        }
+
+        fields
+    }
+
+    /// Builds the suggestion modal, prefilled with the values of `current` if given.
+    pub fn modal(
+        title: String,
+        timeout: Duration,
+        current: Option<&Suggestion>,
+    ) -> CreateQuickModal {
+        let mut artist_name = CreateInputText::new(InputTextStyle::Short, "Artist name", "")
+            .placeholder("The artist name")
+            .max_length(MAX_NAME_LENGTH as u16);
+        let mut album_name = CreateInputText::new(InputTextStyle::Short, "Album name", "")
+            .placeholder("The album name")
+            .max_length(MAX_NAME_LENGTH as u16);
+        let mut links = CreateInputText::new(InputTextStyle::Paragraph, "Links", "")
+            .placeholder(
+                "One or more links to the album on any platform.\nEach link should be on a new line.",
+            )
+            .max_length(MAX_TEXT_LENGTH as u16);
+        let mut notes = CreateInputText::new(InputTextStyle::Paragraph, "Notes", "")
+            .placeholder("Any additional notes")
+            .max_length(MAX_TEXT_LENGTH as u16)
+            .required(false);
+
+        if let Some(current) = current {
+            artist_name = artist_name.value(&current.artist_name);
+            album_name = album_name.value(&current.album_name);
+            links = links.value(join_links(&current.links));
+
+            if let Some(current) = &current.notes {
+                notes = notes.value(current);
+            }
+        }
+
+        CreateQuickModal::new(title)
+            .field(artist_name)
+            .field(album_name)
+            .field(links)
+            .field(notes)
+            .timeout(timeout)
+    }
+
+    /// Parses a suggestion modal response.
+    ///
+    /// Invalid links are reported as a [`LinkError`], which can be shown to the submitter.
+    pub fn parse_response(
+        response: &QuickModalResponse,
+        category: &str,
+        link_denylist: &[String],
+    ) -> Result<Suggestion> {
         if !(3..=4).contains(&response.inputs.len()) {
             return Err(eyre!("invalid form structure"));
         };
 
+        Suggestion::parse(
+            response.interaction.user.id,
+            response.interaction.user.name.clone(),
+            &response.inputs,
+            category,
+            link_denylist,
+        )
+    }
+
+    /// Parses the artist name, album name, links and optional notes of a suggestion by a user.
+    ///
+    /// The inputs are checked against the limits of the suggestion modal.
+    /// Invalid links are reported as a [`LinkError`], which can be shown to the submitter.
+    pub fn parse(
+        user_id: UserId,
+        username: String,
+        inputs: &[String],
+        category: &str,
+        link_denylist: &[String],
+    ) -> Result<Suggestion> {
+        let [artist_name, album_name, links, notes @ ..] = inputs else {
+            return Err(eyre!("missing suggestion fields"));
+        };
+
+        for (name, input, max_length) in [
+            ("artist name", artist_name, MAX_NAME_LENGTH),
+            ("album name", album_name, MAX_NAME_LENGTH),
+        ] {
+            if input.is_empty() {
+                return Err(eyre!("the {name} is empty"));
+            }
+
+            if input.chars().count() > max_length {
+                return Err(eyre!("the {name} is longer than {max_length} characters"));
+            }
+        }
+
+        for (name, input) in [("links", Some(links)), ("notes", notes.first())] {
+            if input.is_some_and(|input| input.chars().count() > MAX_TEXT_LENGTH) {
+                return Err(eyre!(
+                    "the {name} are longer than {MAX_TEXT_LENGTH} characters"
+                ));
+            }
+        }
+
+        let links = parse_links(links, link_denylist)?;
+
         Ok(Suggestion {
             id: 0,
-            user_id: response.interaction.user.id,
-            username: response.interaction.user.name.clone(),
-            artist_name: response.inputs[0].clone(),
-            album_name: response.inputs[1].clone(),
-            links: response.inputs[2].clone(),
-            notes: response.inputs.get(3).cloned().filter(|s| !s.is_empty()),
-            internal,
+            user_id,
+            username,
+            artist_name: artist_name.clone(),
+            album_name: album_name.clone(),
+            links,
+            notes: notes.first().cloned().filter(|s| !s.is_empty()),
+            category: category.to_string(),
         })
     }
 }
 
+/// An approved suggestion waiting to be announced.
+pub struct QueueEntry {
+    pub id: u64,
+    pub user_id: UserId,
+    pub username: String,
+    pub artist_name: String,
+    pub album_name: String,
+    pub approved_at: Option<NaiveDateTime>,
+}
+
+/// The number of suggestions in a category.
+pub struct CategoryStats {
+    pub category: String,
+    pub pending: u64,
+    pub queued: u64,
+    pub archived: u64,
+}
+
+/// The row counts of a guild.
+pub struct Stats {
+    pub categories: Vec<CategoryStats>,
+    pub blocked_users: u64,
+    pub blocked_artists: u64,
+    pub blocked_attempts: u64,
+    pub notices: u64,
+    pub slots: u64,
+}
+
+/// A suggestion from `deleted_suggestions`.
+pub struct ArchivedSuggestion {
+    pub suggestion: Suggestion, // `id` is the ID in `deleted_suggestions`
+    pub status: PollStatus,
+    pub timestamp: NaiveDateTime,
+    pub archived_at: Option<NaiveDateTime>,
+    pub reason: Option<String>,
+}
+
+/// A user who may not submit suggestions.
+pub struct BlockedUser {
+    pub user_id: UserId,
+    pub reason: Option<String>,
+    pub timestamp: NaiveDateTime,
+}
+
+/// An artist who may not be suggested.
+pub struct BlockedArtist {
+    pub artist_name: String,
+    pub reason: Option<String>,
+    pub timestamp: NaiveDateTime,
+}
+
+/// A submission rejected because of the blocklist.
+pub struct BlockedAttempt {
+    pub user_id: UserId,
+    pub username: String,
+    pub artist_name: Option<String>, // `None` if the user was rejected before filling out the form
+    pub album_name: Option<String>,
+    pub category: String,
+}
+
+/// A recorded submission rejected because of the blocklist.
+pub struct RecordedBlockedAttempt {
+    pub attempt: BlockedAttempt,
+    pub timestamp: NaiveDateTime,
+}
+
+/// Filters for searching archived suggestions. Unset filters match everything.
+#[derive(Default)]
+pub struct HistoryFilter {
+    /// Words matched against the artist, album and submitter names.
+    pub text: Option<String>,
+    pub category: Option<String>,
+    pub status: Option<PollStatus>,
+    pub user_id: Option<UserId>,
+}
+
 #[derive(Clone)]
 pub struct Poll {
     pub id: u64,
     pub message_id: MessageId,
     pub author_id: UserId,
-    pub internal: bool,
+    pub category: String,
     pub status: PollStatus,
 }
 
 impl Poll {
-    pub fn new(id: u64, message_id: MessageId, author_id: UserId, internal: bool) -> Poll {
+    pub fn new(id: u64, message_id: MessageId, author_id: UserId, category: String) -> Poll {
         Poll {
             id,
             message_id,
             author_id,
-            internal,
+            category,
             status: PollStatus::default(),
         }
     }
+
+    /// Returns whether the poll is pending and its voting `window` has passed by `now`.
+    ///
+    /// Polls never expire if the window is zero.
+    pub fn is_expired(&self, window: Duration, now: DateTime<Utc>) -> bool {
+        // a poll's creation time is encoded in its message ID
+        !window.is_zero()
+            && matches!(self.status, PollStatus::Pending { .. })
+            && self.message_id.created_at().unix_timestamp()
+                <= now.timestamp() - window.as_secs() as i64
+    }
 }
 
 #[derive(Clone)]
 pub enum PollStatus {
-    Pending { votes: HashSet<UserId> },
+    Pending {
+        upvotes: HashSet<UserId>,
+        downvotes: HashSet<UserId>,
+    },
     Completed,
     Revoked,
     Vetoed,
+    Expired,
+}
+
+/// Parses comma-separated user IDs.
+fn parse_votes(votes: Option<String>) -> HashSet<UserId> {
+    votes
+        .iter()
+        .flat_map(|votes| votes.split_terminator(","))
+        .map(|id| id.parse().unwrap())
+        .collect()
+}
+
+/// Returns whether a poll with the given tallies passes under a rule.
+fn passes(
+    rule: PollRule,
+    upvotes: usize,
+    downvotes: usize,
+    threshold: usize,
+    approval_percent: usize,
+) -> bool {
+    match rule {
+        PollRule::Upvotes => upvotes >= threshold,
+        PollRule::NetScore => upvotes as isize - downvotes as isize >= threshold as isize,
+        PollRule::Approval => {
+            let voters = upvotes + downvotes;
+            voters >= threshold && voters > 0 && upvotes * 100 >= approval_percent * voters
+        }
+    }
 }
 
 impl PollStatus {
-    pub fn parse(status: u64, votes: Option<String>) -> Result<PollStatus> {
-        match (status, votes) {
-            (0, Some(votes)) => Ok(PollStatus::Pending {
-                votes: votes
-                    .split_terminator(",")
-                    .map(|id| id.parse().unwrap())
-                    .collect(),
-            }),
-            (0, None) => Ok(PollStatus::Pending {
-                votes: HashSet::new(),
+    pub fn parse(
+        status: u64,
+        votes: Option<String>,
+        downvotes: Option<String>,
+    ) -> Result<PollStatus> {
+        match (status, votes, downvotes) {
+            (0, votes, downvotes) => Ok(PollStatus::Pending {
+                upvotes: parse_votes(votes),
+                downvotes: parse_votes(downvotes),
             }),
-            (1, None) => Ok(PollStatus::Completed),
-            (2, None) => Ok(PollStatus::Revoked),
-            (3, None) => Ok(PollStatus::Vetoed),
+            (1, None, None) => Ok(PollStatus::Completed),
+            (2, None, None) => Ok(PollStatus::Revoked),
+            (3, None, None) => Ok(PollStatus::Vetoed),
+            (4, None, None) => Ok(PollStatus::Expired),
             _ => Err(eyre!("invalid poll status")),
         }
     }
 
-    pub fn format(&self, threshold: usize) -> (String, Color) {
+    /// Encodes the status as stored in the `status`, `votes` and `downvotes` columns.
+    pub fn encode(&self) -> (u64, Option<String>, Option<String>) {
         match self {
-            PollStatus::Pending { votes } => (
-                format!("Pending ({}/{threshold}) 🗳️", votes.len()),
-                Color::BLUE,
+            PollStatus::Pending { upvotes, downvotes } => (
+                0,
+                Some(upvotes.iter().join(",")),
+                Some(downvotes.iter().join(",")),
             ),
+            PollStatus::Completed => (1, None, None),
+            PollStatus::Revoked => (2, None, None),
+            PollStatus::Vetoed => (3, None, None),
+            PollStatus::Expired => (4, None, None),
+        }
+    }
+
+    /// Returns whether a pending poll has enough votes to pass.
+    pub fn passes(&self, config: &Config, category: &Category) -> bool {
+        match self {
+            PollStatus::Pending { upvotes, downvotes } => passes(
+                config.poll_rule,
+                upvotes.len(),
+                downvotes.len(),
+                config.poll_threshold(category),
+                config.poll_approval_percent,
+            ),
+            _ => false,
+        }
+    }
+
+    pub fn format(&self, config: &Config, category: &Category) -> (String, Color) {
+        let threshold = config.poll_threshold(category);
+
+        match self {
+            PollStatus::Pending { upvotes, downvotes } => {
+                let (up, down) = (upvotes.len(), downvotes.len());
+                let tally = match config.poll_rule {
+                    PollRule::Upvotes => format!("👍 {up}/{threshold}, 👎 {down}"),
+                    PollRule::NetScore => format!(
+                        "👍 {up}, 👎 {down}, score {}/{threshold}",
+                        up as isize - down as isize
+                    ),
+                    PollRule::Approval => format!(
+                        "👍 {up}, 👎 {down}, {}%/{}% of {}/{threshold} voters",
+                        (up * 100).checked_div(up + down).unwrap_or_default(),
+                        config.poll_approval_percent,
+                        up + down
+                    ),
+                };
+
+                (format!("Pending ({tally}) 🗳️"), Color::BLUE)
+            }
             PollStatus::Completed => ("Completed ✅".into(), Color::from_rgb(87, 242, 135)),
             PollStatus::Revoked => ("Revoked 🗑️".into(), Color::RED),
             PollStatus::Vetoed => ("Vetoed 🛑".into(), Color::RED),
+            PollStatus::Expired => ("Expired ⌛".into(), Color::DARK_GREY),
         }
     }
+
+    /// Builds the poll buttons for this status.
+    pub fn components(&self) -> Vec<CreateActionRow> {
+        let (vote_disabled, edit_disabled, disabled) = match self {
+            PollStatus::Pending { .. } => (false, false, false),
+            PollStatus::Completed => (true, true, false),
+            PollStatus::Revoked | PollStatus::Vetoed | PollStatus::Expired => (true, true, true),
+        };
+
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new("poll:upvote")
+                .label("Upvote")
+                .emoji('👍')
+                .disabled(vote_disabled),
+            CreateButton::new("poll:downvote")
+                .label("Downvote")
+                .emoji('👎')
+                .disabled(vote_disabled),
+            CreateButton::new("poll:edit")
+                .label("Edit")
+                .emoji('✏')
+                .disabled(edit_disabled),
+            CreateButton::new("poll:revoke")
+                .label("Revoke")
+                .emoji('🗑')
+                .disabled(disabled),
+            CreateButton::new("poll:veto")
+                .label("Veto")
+                .emoji('🛑')
+                .disabled(disabled),
+        ])]
+    }
 }
 
 impl Default for PollStatus {
     fn default() -> Self {
         PollStatus::Pending {
-            votes: HashSet::new(),
+            upvotes: HashSet::new(),
+            downvotes: HashSet::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_status_encoding() {
+        let status = PollStatus::Pending {
+            upvotes: HashSet::from([UserId::new(1), UserId::new(2)]),
+            downvotes: HashSet::from([UserId::new(3)]),
+        };
+
+        let (status, votes, downvotes) = status.encode();
+        let PollStatus::Pending { upvotes, downvotes } =
+            PollStatus::parse(status, votes, downvotes).unwrap()
+        else {
+            panic!("expected a pending status");
+        };
+
+        assert_eq!(upvotes, HashSet::from([UserId::new(1), UserId::new(2)]));
+        assert_eq!(downvotes, HashSet::from([UserId::new(3)]));
+
+        // polls stored before downvotes existed
+        assert!(matches!(
+            PollStatus::parse(0, Some("1,2".into()), None),
+            Ok(PollStatus::Pending { upvotes, downvotes }) if upvotes.len() == 2 && downvotes.is_empty()
+        ));
+        assert!(matches!(
+            PollStatus::parse(3, None, None),
+            Ok(PollStatus::Vetoed)
+        ));
+        assert!(PollStatus::parse(1, Some("1".into()), None).is_err());
+    }
+
+    #[test]
+    fn test_passes() {
+        const TESTS: &[(PollRule, usize, usize, bool)] = &[
+            (PollRule::Upvotes, 3, 10, true),
+            (PollRule::Upvotes, 2, 0, false),
+            (PollRule::NetScore, 5, 2, true),
+            (PollRule::NetScore, 5, 3, false),
+            (PollRule::Approval, 2, 1, true),
+            (PollRule::Approval, 1, 1, false),
+            (PollRule::Approval, 2, 0, false),
+        ];
+
+        for (rule, upvotes, downvotes, expected) in TESTS {
+            assert_eq!(passes(*rule, *upvotes, *downvotes, 3, 60), *expected);
+        }
+    }
+
+    #[test]
+    fn test_is_expired() {
+        // the seconds since the poll was created, the voting window, and whether the poll is still pending
+        const TESTS: &[(u64, u64, bool, bool)] = &[
+            (59, 60, true, false),
+            (60, 60, true, true),
+            (3600, 60, true, true),
+            (3600, 0, true, false),
+            (3600, 60, false, false),
+        ];
+
+        let now = DateTime::from_timestamp(1_750_000_000, 0).unwrap();
+
+        for (age, window, pending, expected) in TESTS {
+            // message IDs are snowflakes with the milliseconds since the Discord epoch in the upper bits
+            let created_at = (now.timestamp() as u64 - age) * 1000 - 1_420_070_400_000;
+            let mut poll = Poll::new(
+                1,
+                MessageId::new(created_at << 22),
+                UserId::new(1),
+                "external".into(),
+            );
+
+            if !pending {
+                poll.status = PollStatus::Expired;
+            }
+
+            assert_eq!(
+                poll.is_expired(Duration::from_secs(*window), now),
+                *expected
+            );
         }
     }
 }
@@ -1,21 +1,9 @@
 use std::time::Duration;
 
 use poise::serenity_prelude::*;
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serializer};
 use tracing::error;
 
-pub fn artist(internal: bool) -> &'static str {
-    if internal { "internal" } else { "external" }
-}
-
-pub fn artist_capital(internal: bool) -> &'static str {
-    if internal {
-        "Biweekly Internal"
-    } else {
-        "Weekly External"
-    }
-}
-
 pub fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
 where
     D: Deserializer<'de>,
@@ -23,6 +11,13 @@ where
     Ok(Duration::from_secs(u64::deserialize(deserializer)?))
 }
 
+pub fn serialize_duration<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u64(duration.as_secs())
+}
+
 /// Gets the icon URL of a user.
 ///
 /// If the user is not found, returns a default icon URL.
@@ -0,0 +1,139 @@
+use poise::serenity_prelude::*;
+
+use crate::config::Config;
+
+/// The permissions the bot needs in every channel it posts in and edits its messages in.
+const CHANNEL_PERMISSIONS: Permissions = Permissions::VIEW_CHANNEL
+    .union(Permissions::SEND_MESSAGES)
+    .union(Permissions::EMBED_LINKS);
+
+/// Checks the config of every guild and formats the problems as one report.
+///
+/// Without `http`, only the problems that can be found without connecting to Discord are checked.
+/// Returns `None` if there are no problems.
+pub async fn check_configs(http: Option<&Http>, configs: &[Config]) -> Option<String> {
+    let mut count = 0;
+    let mut report = String::new();
+
+    for config in configs {
+        let problems = check_config(http, config).await;
+
+        if problems.is_empty() {
+            continue;
+        }
+
+        count += problems.len();
+        report += &format!("\nguild {}:", config.guild);
+
+        for problem in problems {
+            report += &format!("\n  - {problem}");
+        }
+    }
+
+    (count != 0).then(|| format!("found {count} problem(s) in the config:{report}"))
+}
+
+/// Checks the config of a guild, against the live guild if `http` is given, and returns every problem found.
+async fn check_config(http: Option<&Http>, config: &Config) -> Vec<String> {
+    let mut problems = config.problems();
+
+    let Some(http) = http else {
+        return problems;
+    };
+
+    let guild = match config.guild.to_partial_guild(http).await {
+        Ok(guild) => guild,
+        Err(e) => {
+            problems.push(format!("the guild can't be fetched: {e}"));
+            return problems;
+        }
+    };
+
+    // roles
+    let mut roles = vec![(config.facilitator_role, "`facilitator-role-id`".to_string())];
+    roles.extend(
+        config
+            .announcement_role
+            .map(|role| (role, "`announcement-role-id`".to_string())),
+    );
+    roles.extend(config.categories.iter().filter_map(|category| {
+        category.ping_role.map(|role| {
+            (
+                role,
+                format!("`ping-role-id` of category `{}`", category.id),
+            )
+        })
+    }));
+
+    for (role, key) in roles {
+        if !guild.roles.contains_key(&role) {
+            problems.push(format!("{key} {role} is not a role of the guild"));
+        }
+    }
+
+    // channels
+    let member = match http.get_current_user().await {
+        Ok(user) => guild.member(http, user.id).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    let member = match member {
+        Ok(member) => Some(member),
+        Err(e) => {
+            problems.push(format!(
+                "the bot's member can't be fetched, so channel permissions are not checked: {e}"
+            ));
+            None
+        }
+    };
+
+    let mut channels = Vec::new();
+
+    for category in &config.categories {
+        channels.push((
+            category.poll_channel,
+            format!("`poll-channel-id` of category `{}`", category.id),
+        ));
+        channels.push((
+            category.announcement_channel,
+            format!("`announcement-channel-id` of category `{}`", category.id),
+        ));
+    }
+
+    channels.extend(
+        config
+            .facilitator_channel
+            .map(|channel| (channel, "`facilitator-channel-id`".to_string())),
+    );
+
+    for (channel, key) in channels {
+        let channel = match channel.to_channel(http).await {
+            Ok(Channel::Guild(channel)) if channel.guild_id == guild.id => channel,
+            Ok(_) => {
+                problems.push(format!("{key} {channel} is not a channel of the guild"));
+                continue;
+            }
+            Err(e) => {
+                problems.push(format!("{key} {channel} can't be fetched: {e}"));
+                continue;
+            }
+        };
+
+        let Some(member) = &member else {
+            continue;
+        };
+
+        let missing = CHANNEL_PERMISSIONS - guild.user_permissions_in(&channel, member);
+
+        if !missing.is_empty() {
+            problems.push(format!(
+                "the bot lacks the {} permission(s) in {key} {} (#{})",
+                missing.get_permission_names().join(", "),
+                channel.id,
+                channel.name
+            ));
+        }
+    }
+
+    problems
+}